@@ -1,13 +1,88 @@
+use std::fmt::Write;
+
 use codspeed_criterion_compat::*;
 use css_module_lexer::collect_dependencies;
+use css_module_lexer::Lexer;
 use css_module_lexer::Mode;
+use css_module_lexer::Visitor;
 
 const BOOTSTRAP: &str = include_str!("../fixtures/bootstrap.min.css");
+const BOOTSTRAP_FULL: &str = include_str!("../fixtures/bootstrap.css");
+
+struct Noop;
+
+impl Visitor<'_> for Noop {}
+
+fn comments() -> String {
+    let text = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor. ";
+    let mut css = String::new();
+    for i in 0..500 {
+        writeln!(
+            css,
+            "/*\n * {}\n */\n.a-{i} {{ color: red; }}",
+            text.repeat(20)
+        )
+        .unwrap();
+    }
+    css
+}
+
+fn data_uris() -> String {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let base64: String = (0..8000usize)
+        .map(|i| alphabet[i * 7 % 64] as char)
+        .collect();
+    let mut css = String::new();
+    for i in 0..100 {
+        writeln!(
+            css,
+            ".icon-{i} {{ background: url(data:image/png;base64,{base64}); }}"
+        )
+        .unwrap();
+        writeln!(
+            css,
+            ".quoted-{i} {{ background: url(\"data:image/png;base64,{base64}\"); }}"
+        )
+        .unwrap();
+    }
+    css
+}
+
+fn indentation() -> String {
+    let mut css = String::new();
+    for i in 0..2000 {
+        let indent = " ".repeat(4 * (i % 8));
+        writeln!(
+            css,
+            "{indent}.a-{i} {{\n{indent}    color: red;\n{indent}}}"
+        )
+        .unwrap();
+    }
+    css
+}
 
 fn benchmark(c: &mut Criterion) {
     c.bench_function("bootstrap", |b| {
         b.iter(|| collect_dependencies(black_box(BOOTSTRAP), Mode::Local))
     });
+    c.bench_function("bootstrap lex", |b| {
+        b.iter(|| Lexer::new(black_box(BOOTSTRAP)).lex(&mut Noop))
+    });
+    c.bench_function("bootstrap unminified", |b| {
+        b.iter(|| collect_dependencies(black_box(BOOTSTRAP_FULL), Mode::Local))
+    });
+    let comments = comments();
+    c.bench_function("comments", |b| {
+        b.iter(|| collect_dependencies(black_box(&comments), Mode::Local))
+    });
+    let indentation = indentation();
+    c.bench_function("indentation", |b| {
+        b.iter(|| collect_dependencies(black_box(&indentation), Mode::Local))
+    });
+    let data_uris = data_uris();
+    c.bench_function("data uris", |b| {
+        b.iter(|| collect_dependencies(black_box(&data_uris), Mode::Local))
+    });
 }
 
 criterion_group!(benches, benchmark);
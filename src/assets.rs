@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::base64;
+use crate::collect_dependencies;
+use crate::hash::fnv1a64;
+use crate::json;
+use crate::path;
+use crate::rebase::is_rebasable;
+use crate::rebase::rewrite;
+use crate::rebase::unescape;
+use crate::Dependency;
+use crate::EditBuffer;
+use crate::HandleWarning;
+use crate::Mode;
+use crate::Replacement;
+use crate::Warning;
+use crate::WarningKind;
+
+pub trait LoadAsset {
+    fn load_asset(&mut self, path: &str) -> Option<Vec<u8>>;
+}
+
+impl<F: FnMut(&str) -> Option<Vec<u8>>> LoadAsset for F {
+    fn load_asset(&mut self, path: &str) -> Option<Vec<u8>> {
+        self(path)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    root: PathBuf,
+}
+
+impl FileSystem {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl LoadAsset for FileSystem {
+    fn load_asset(&mut self, path: &str) -> Option<Vec<u8>> {
+        if path.split('/').any(|segment| segment == "..") {
+            return None;
+        }
+        fs::read(self.root.join(path)).ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asset {
+    pub source: String,
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+pub fn mime_type(path: &str) -> Option<&'static str> {
+    let extension = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "ico" | "cur" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "eot" => "application/vnd.ms-fontobject",
+        _ => return None,
+    })
+}
+
+pub fn data_uri(path: &str, content: &[u8]) -> String {
+    let mime = mime_type(path).unwrap_or("application/octet-stream");
+    match std::str::from_utf8(content) {
+        Ok(svg) if mime == "image/svg+xml" => {
+            let mut result = String::from("data:image/svg+xml,");
+            for byte in svg.trim().bytes() {
+                match byte {
+                    b'"'
+                    | b'\''
+                    | b'%'
+                    | b'#'
+                    | b'<'
+                    | b'>'
+                    | b'{'
+                    | b'}'
+                    | b'|'
+                    | b'\\'
+                    | b'^'
+                    | b'`'
+                    | b'('
+                    | b')'
+                    | b' '
+                    | 0..=0x1f
+                    | 0x7f.. => result += &format!("%{byte:02X}"),
+                    byte => result.push(byte as char),
+                }
+            }
+            result
+        }
+        _ => format!("data:{mime};base64,{}", base64::encode(content)),
+    }
+}
+
+fn hashed_name(path: &str, content: &[u8]) -> String {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let hash = format!("{:016x}", fnv1a64(content));
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => {
+            format!("{stem}.{}.{extension}", &hash[..8])
+        }
+        _ => format!("{file_name}.{}", &hash[..8]),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetPipeline<L> {
+    loader: L,
+    inline_limit: usize,
+    public_path: String,
+    assets: Vec<Asset>,
+    urls: HashMap<String, Option<String>>,
+}
+
+impl<L: LoadAsset> AssetPipeline<L> {
+    pub fn new(loader: L) -> Self {
+        Self {
+            loader,
+            inline_limit: 0,
+            public_path: String::new(),
+            assets: Vec::new(),
+            urls: HashMap::new(),
+        }
+    }
+
+    // Assets smaller than `limit` bytes are inlined as data URIs
+    pub fn inline_limit(mut self, limit: usize) -> Self {
+        self.inline_limit = limit;
+        self
+    }
+
+    pub fn public_path(mut self, public_path: &str) -> Self {
+        self.public_path = public_path.to_string();
+        self
+    }
+
+    pub fn assets(&self) -> &[Asset] {
+        &self.assets
+    }
+
+    fn url(&mut self, path: &str, inline: bool) -> Option<String> {
+        let key = format!("{}{path}", if inline { "" } else { "?" });
+        if let Some(url) = self.urls.get(&key) {
+            return url.clone();
+        }
+        let url = self.loader.load_asset(path).map(|content| {
+            if inline && content.len() < self.inline_limit {
+                return data_uri(path, &content);
+            }
+            let name = hashed_name(path, &content);
+            if !self.assets.iter().any(|asset| asset.source == path) {
+                self.assets.push(Asset {
+                    source: path.to_string(),
+                    name: name.clone(),
+                    content,
+                });
+            }
+            format!("{}{name}", self.public_path)
+        });
+        self.urls.insert(key, url.clone());
+        url
+    }
+
+    // Urls are resolved against `path`, which is relative to the root of the loader, absolute
+    // and external urls are left alone
+    pub fn process<'s>(
+        &mut self,
+        input: &'s str,
+        path: &str,
+        mode: Mode,
+        mut handle_warning: impl HandleWarning<'s>,
+    ) -> String {
+        let (dependencies, warnings) = collect_dependencies(input, mode);
+        for warning in warnings {
+            handle_warning.handle_warning(warning);
+        }
+        let mut buffer = EditBuffer::new(input);
+        for dependency in &dependencies {
+            let Dependency::Url { request, range, .. } = dependency else {
+                continue;
+            };
+            let request_path = unescape(request);
+            if !is_rebasable(&request_path) {
+                continue;
+            }
+            let (asset_path, suffix) =
+                request_path.split_at(request_path.find(['?', '#']).unwrap_or(request_path.len()));
+            let asset_path = path::join(path, asset_path);
+            // Urls with a query or fragment are always emitted as files to keep the suffix
+            let Some(url) = self.url(&asset_path, suffix.is_empty()) else {
+                handle_warning.handle_warning(Warning::new(
+                    range.clone(),
+                    WarningKind::MissingAsset { request },
+                ));
+                continue;
+            };
+            let text = &input[range.start as usize..range.end as usize];
+            let url = format!("{url}{suffix}");
+            let _ = buffer.edit(Replacement::new(range.clone(), rewrite(text, &url)));
+        }
+        buffer.to_string()
+    }
+
+    pub fn manifest_json(&self) -> String {
+        let mut out = String::from("{");
+        let mut first = true;
+        for asset in &self.assets {
+            json::write_key(&mut out, &mut first, &asset.source);
+            json::write_string(&mut out, &format!("{}{}", self.public_path, asset.name));
+        }
+        out.push('}');
+        out
+    }
+}
@@ -0,0 +1,337 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::rebase::import_request_range;
+use crate::rebase::is_rebasable;
+use crate::rebase::rebase_dependency;
+use crate::EdgeKind;
+use crate::EditBuffer;
+use crate::EditError;
+use crate::Module;
+use crate::ModuleGraph;
+use crate::ModuleId;
+use crate::OwnedDependency;
+use crate::Range;
+use crate::Replacement;
+use crate::Warning;
+use crate::WarningKind;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct Condition<'g> {
+    layer: Option<&'g str>,
+    supports: Option<&'g str>,
+    media: Option<&'g str>,
+}
+
+impl Condition<'_> {
+    fn is_empty(&self) -> bool {
+        self.layer.is_none() && self.supports.is_none() && self.media.is_none()
+    }
+}
+
+// Joins the conditions of nested imports into the conditions of one `@import`, like
+// postcss-import does. An anonymous layer can't have named layers inside it in an `@import`,
+// so it makes the whole layer anonymous
+fn import_conditions(conditions: &[&Condition]) -> String {
+    let mut result = String::new();
+    let layers: Vec<&str> = conditions.iter().filter_map(|c| c.layer).collect();
+    if layers.iter().any(|layer| layer.is_empty()) {
+        result += " layer";
+    } else if !layers.is_empty() {
+        result += &format!(" layer({})", layers.join("."));
+    }
+    let supports: Vec<&str> = conditions.iter().filter_map(|c| c.supports).collect();
+    match supports.as_slice() {
+        [] => {}
+        [supports] => result += &format!(" supports({supports})"),
+        supports => {
+            let supports: Vec<String> = supports.iter().map(|s| format!("({s})")).collect();
+            result += &format!(" supports({})", supports.join(" and "));
+        }
+    }
+    let mut media: Vec<String> = Vec::new();
+    for list in conditions.iter().filter_map(|c| c.media) {
+        let queries = list.split(',').map(str::trim);
+        media = if media.is_empty() {
+            queries.map(str::to_string).collect()
+        } else {
+            media
+                .iter()
+                .flat_map(|outer| {
+                    queries
+                        .clone()
+                        .map(move |inner| format!("{outer} and {inner}"))
+                })
+                .collect()
+        };
+    }
+    if !media.is_empty() {
+        result.push(' ');
+        result += &media.join(", ");
+    }
+    result
+}
+
+// An import that is hoisted to the top of the bundle, `conditions` are the ones of the imports
+// of the files it is in
+#[derive(Debug, Clone)]
+struct External<'g> {
+    rule: Cow<'g, str>,
+    conditions: Vec<Condition<'g>>,
+}
+
+// What a module and the modules it imports add to the bundle, conditions are relative to the
+// import of the module. Each file and condition is only kept at its last position, and each
+// external import at its first one
+#[derive(Debug, Default)]
+struct Collected<'g> {
+    entries: Vec<(ModuleId, Vec<Condition<'g>>)>,
+    external: Vec<External<'g>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Bundler<'g> {
+    graph: &'g ModuleGraph,
+    output: Option<&'g str>,
+}
+
+impl<'g> Bundler<'g> {
+    pub fn new(graph: &'g ModuleGraph) -> Self {
+        Self {
+            graph,
+            output: None,
+        }
+    }
+
+    // Relative urls of every inlined file are rewritten to be relative to `output`
+    pub fn rebase_urls(mut self, output: &'g str) -> Self {
+        self.output = Some(output);
+        self
+    }
+
+    fn rebase(
+        &self,
+        module: &'g Module,
+        dependency: &'g OwnedDependency,
+    ) -> Option<Replacement<'g>> {
+        let output = self.output?;
+        let source = module.source();
+        rebase_dependency(source, &dependency.as_dependency(), module.id(), output)
+    }
+
+    // The result of each module is only collected once, a module that is imported again while
+    // it is collected closes a cycle and is skipped
+    fn collect(
+        &self,
+        module_id: ModuleId,
+        collected: &mut HashMap<ModuleId, Collected<'g>>,
+        stack: &mut Vec<ModuleId>,
+        handle_warning: &mut impl FnMut(ModuleId, Warning<'g>),
+    ) {
+        let module = self.graph.module(module_id);
+        stack.push(module_id);
+        let mut result = Collected::default();
+        for edge in module.edges() {
+            let OwnedDependency::Import {
+                request,
+                range,
+                layer,
+                supports,
+                media,
+            } = &module.dependencies()[edge.dependency]
+            else {
+                continue;
+            };
+            let condition = Condition {
+                layer: layer.as_deref(),
+                supports: supports.as_deref(),
+                media: media.as_deref().map(str::trim).filter(|m| !m.is_empty()),
+            };
+            match edge.target {
+                None => {
+                    if is_rebasable(request) {
+                        handle_warning(
+                            module_id,
+                            Warning::new(range.clone(), WarningKind::MissingImport { request }),
+                        );
+                    }
+                    let mut rule =
+                        Cow::Borrowed(&module.source()[range.start as usize..range.end as usize]);
+                    if let Some(replacement) =
+                        self.rebase(module, &module.dependencies()[edge.dependency])
+                    {
+                        let start = (replacement.range.start - range.start) as usize;
+                        let end = (replacement.range.end - range.start) as usize;
+                        rule = Cow::Owned(format!(
+                            "{}{}{}",
+                            &rule[..start],
+                            replacement.content,
+                            &rule[end..]
+                        ));
+                    }
+                    result.external.push(External {
+                        rule,
+                        conditions: vec![condition],
+                    });
+                }
+                Some(target) if stack.contains(&target) => {
+                    let position = stack.iter().position(|&id| id == target).unwrap();
+                    handle_warning(
+                        module_id,
+                        Warning::new(
+                            range.clone(),
+                            WarningKind::DependencyCycle {
+                                name: request,
+                                modules: stack.len() - position,
+                            },
+                        ),
+                    )
+                }
+                Some(target) => {
+                    if !collected.contains_key(&target) {
+                        self.collect(target, collected, stack, handle_warning);
+                    }
+                    let prefix = |conditions: &[Condition<'g>]| {
+                        let mut prefixed = Vec::with_capacity(conditions.len() + 1);
+                        if !condition.is_empty() {
+                            prefixed.push(condition.clone());
+                        }
+                        prefixed.extend_from_slice(conditions);
+                        prefixed
+                    };
+                    let target = &collected[&target];
+                    for (id, conditions) in &target.entries {
+                        result.entries.push((*id, prefix(conditions)));
+                    }
+                    for external in &target.external {
+                        result.external.push(External {
+                            rule: external.rule.clone(),
+                            conditions: prefix(&external.conditions),
+                        });
+                    }
+                }
+            }
+        }
+        stack.pop();
+        result.entries.push((module_id, Vec::new()));
+        let mut seen = HashSet::new();
+        result.entries.reverse();
+        result.entries.retain(|entry| seen.insert(entry.clone()));
+        result.entries.reverse();
+        let mut seen = HashSet::new();
+        result
+            .external
+            .retain(|external| seen.insert((external.rule.clone(), external.conditions.clone())));
+        collected.insert(module_id, result);
+    }
+
+    // The import rule with the conditions of the imports of the files it is in, the rule is
+    // kept as it is when they have none
+    fn external_rule(external: &External) -> String {
+        let (own, outer) = external.conditions.split_last().unwrap();
+        if outer.iter().all(Condition::is_empty) {
+            return external.rule.to_string();
+        }
+        let end = import_request_range(&external.rule).map_or(external.rule.len(), |(_, end)| end);
+        let conditions: Vec<&Condition> = outer.iter().chain([own]).collect();
+        format!(
+            "{}{};",
+            &external.rule[..end],
+            import_conditions(&conditions)
+        )
+    }
+
+    // An edit that can't be applied is reported and the source is kept as it is there
+    fn content(
+        &self,
+        module_id: ModuleId,
+        handle_warning: &mut impl FnMut(ModuleId, Warning<'g>),
+    ) -> String {
+        let module = self.graph.module(module_id);
+        let source = module.source();
+        let mut buffer = EditBuffer::new(source);
+        let mut report = |result: Result<(), EditError>, range: Range| {
+            if let Err(error) = result {
+                handle_warning(
+                    module_id,
+                    Warning::new(range, WarningKind::InvalidEdit { error }),
+                );
+            }
+        };
+        for edge in module.edges() {
+            if edge.kind == EdgeKind::Url {
+                if let Some(replacement) =
+                    self.rebase(module, &module.dependencies()[edge.dependency])
+                {
+                    let range = replacement.range.clone();
+                    report(buffer.edit(replacement), range);
+                }
+                continue;
+            }
+            if edge.kind != EdgeKind::Import {
+                continue;
+            }
+            let Some(range) = &edge.range else {
+                continue;
+            };
+            let mut end = range.end;
+            if source[end as usize..].starts_with("\r\n") {
+                end += 2;
+            } else if source[end as usize..].starts_with('\n') {
+                end += 1;
+            }
+            report(buffer.remove(Range::new(range.start, end)), range.clone());
+        }
+        buffer.to_string()
+    }
+
+    // Imports that can't be resolved are hoisted verbatim to the top of the bundle, a file
+    // imported more than once with the same conditions is only kept at its last position
+    pub fn bundle(
+        &self,
+        entry: ModuleId,
+        mut handle_warning: impl FnMut(ModuleId, Warning<'g>),
+    ) -> String {
+        let mut collected = HashMap::new();
+        self.collect(entry, &mut collected, &mut Vec::new(), &mut handle_warning);
+        let Collected { entries, external } = collected.remove(&entry).unwrap();
+
+        let mut result = String::new();
+        let mut seen = HashSet::new();
+        for external in &external {
+            let rule = Self::external_rule(external);
+            if seen.insert(rule.clone()) {
+                result += &rule;
+                result.push('\n');
+            }
+        }
+        for (module_id, conditions) in entries {
+            let mut depth = 0;
+            for condition in &conditions {
+                if let Some(media) = condition.media {
+                    result += &format!("@media {media} {{\n");
+                    depth += 1;
+                }
+                if let Some(supports) = condition.supports {
+                    result += &format!("@supports ({supports}) {{\n");
+                    depth += 1;
+                }
+                match condition.layer {
+                    Some("") => result += "@layer {\n",
+                    Some(layer) => result += &format!("@layer {layer} {{\n"),
+                    None => continue,
+                }
+                depth += 1;
+            }
+            result += &self.content(module_id, &mut handle_warning);
+            if !result.is_empty() && !result.ends_with('\n') {
+                result.push('\n');
+            }
+            for _ in 0..depth {
+                result += "}\n";
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,688 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use crate::collect_dependencies;
+use crate::hash::sha256;
+use crate::EditError;
+use crate::Mode;
+use crate::OwnedDependency;
+use crate::OwnedWarning;
+use crate::OwnedWarningKind;
+use crate::Range;
+use crate::UrlRangeKind;
+
+const MAGIC: &[u8; 4] = b"CMLC";
+// Bump when the encoding or the output of the lexer changes without a crate version bump
+const FORMAT: u8 = 2;
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+struct Writer {
+    out: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.out.push(value);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.varint(value.len() as u64);
+        self.out.extend_from_slice(value);
+    }
+
+    fn string(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    fn option(&mut self, value: Option<&str>) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                self.string(value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn strings(&mut self, values: &[String]) {
+        self.varint(values.len() as u64);
+        for value in values {
+            self.string(value);
+        }
+    }
+
+    fn range(&mut self, range: &Range) {
+        self.varint(range.start as u64);
+        self.varint(range.end as u64);
+    }
+
+    fn option_range(&mut self, range: Option<&Range>) {
+        match range {
+            Some(range) => {
+                self.u8(1);
+                self.range(range);
+            }
+            None => self.u8(0),
+        }
+    }
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn u8(&mut self) -> Option<u8> {
+        let (&first, rest) = self.input.split_first()?;
+        self.input = rest;
+        Some(first)
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn bytes(&mut self) -> Option<&[u8]> {
+        let len = usize::try_from(self.varint()?).ok()?;
+        if len > self.input.len() {
+            return None;
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Some(bytes)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        String::from_utf8(self.bytes()?.to_vec()).ok()
+    }
+
+    fn option(&mut self) -> Option<Option<String>> {
+        match self.u8()? {
+            0 => Some(None),
+            1 => Some(Some(self.string()?)),
+            _ => None,
+        }
+    }
+
+    fn strings(&mut self) -> Option<Vec<String>> {
+        let len = self.varint()? as usize;
+        // Every string takes at least one byte, so a corrupted length can't over-allocate
+        let mut values = Vec::with_capacity(len.min(self.input.len()));
+        for _ in 0..len {
+            values.push(self.string()?);
+        }
+        Some(values)
+    }
+
+    fn range(&mut self) -> Option<Range> {
+        let start = u32::try_from(self.varint()?).ok()?;
+        let end = u32::try_from(self.varint()?).ok()?;
+        Some(Range::new(start, end))
+    }
+
+    fn option_range(&mut self) -> Option<Option<Range>> {
+        match self.u8()? {
+            0 => Some(None),
+            1 => Some(Some(self.range()?)),
+            _ => None,
+        }
+    }
+}
+
+fn write_dependency(w: &mut Writer, value: &OwnedDependency) {
+    match value {
+        OwnedDependency::Url {
+            request,
+            range,
+            kind,
+        } => {
+            w.u8(0);
+            w.string(request);
+            w.range(range);
+            w.u8(*kind as u8);
+        }
+        OwnedDependency::Import {
+            request,
+            range,
+            layer,
+            supports,
+            media,
+        } => {
+            w.u8(1);
+            w.string(request);
+            w.range(range);
+            w.option(layer.as_deref());
+            w.option(supports.as_deref());
+            w.option(media.as_deref());
+        }
+        OwnedDependency::Replace { content, range } => {
+            w.u8(2);
+            w.string(content);
+            w.range(range);
+        }
+        OwnedDependency::LocalClass {
+            name,
+            range,
+            explicit,
+        } => {
+            w.u8(3);
+            w.string(name);
+            w.range(range);
+            w.u8(*explicit as u8);
+        }
+        OwnedDependency::LocalId {
+            name,
+            range,
+            explicit,
+        } => {
+            w.u8(4);
+            w.string(name);
+            w.range(range);
+            w.u8(*explicit as u8);
+        }
+        OwnedDependency::LocalVar {
+            name,
+            range,
+            from,
+            from_range,
+        } => {
+            w.u8(5);
+            w.string(name);
+            w.range(range);
+            w.option(from.as_deref());
+            w.option_range(from_range.as_ref());
+        }
+        OwnedDependency::LocalVarDecl { name, range } => {
+            w.u8(6);
+            w.string(name);
+            w.range(range);
+        }
+        OwnedDependency::LocalPropertyDecl { name, range } => {
+            w.u8(7);
+            w.string(name);
+            w.range(range);
+        }
+        OwnedDependency::LocalKeyframes { name, range } => {
+            w.u8(8);
+            w.string(name);
+            w.range(range);
+        }
+        OwnedDependency::LocalKeyframesDecl { name, range } => {
+            w.u8(9);
+            w.string(name);
+            w.range(range);
+        }
+        OwnedDependency::LocalCounterStyle { name, range } => {
+            w.u8(10);
+            w.string(name);
+            w.range(range);
+        }
+        OwnedDependency::LocalCounterStyleDecl { name, range } => {
+            w.u8(11);
+            w.string(name);
+            w.range(range);
+        }
+        OwnedDependency::LocalFontPalette { name, range } => {
+            w.u8(12);
+            w.string(name);
+            w.range(range);
+        }
+        OwnedDependency::LocalFontPaletteDecl { name, range } => {
+            w.u8(13);
+            w.string(name);
+            w.range(range);
+        }
+        OwnedDependency::Composes {
+            local_classes,
+            names,
+            from,
+            range,
+        } => {
+            w.u8(14);
+            w.strings(local_classes);
+            w.strings(names);
+            w.option(from.as_deref());
+            w.range(range);
+        }
+        OwnedDependency::ICSSImportFrom { path } => {
+            w.u8(15);
+            w.string(path);
+        }
+        OwnedDependency::ICSSImportValue { prop, value } => {
+            w.u8(16);
+            w.string(prop);
+            w.string(value);
+        }
+        OwnedDependency::ICSSExportValue { prop, value, range } => {
+            w.u8(17);
+            w.string(prop);
+            w.string(value);
+            w.range(range);
+        }
+    }
+}
+
+fn read_dependency(r: &mut Reader) -> Option<OwnedDependency> {
+    Some(match r.u8()? {
+        0 => OwnedDependency::Url {
+            request: r.string()?,
+            range: r.range()?,
+            kind: match r.u8()? {
+                0 => UrlRangeKind::Function,
+                1 => UrlRangeKind::String,
+                _ => return None,
+            },
+        },
+        1 => OwnedDependency::Import {
+            request: r.string()?,
+            range: r.range()?,
+            layer: r.option()?,
+            supports: r.option()?,
+            media: r.option()?,
+        },
+        2 => OwnedDependency::Replace {
+            content: r.string()?,
+            range: r.range()?,
+        },
+        3 => OwnedDependency::LocalClass {
+            name: r.string()?,
+            range: r.range()?,
+            explicit: r.u8()? != 0,
+        },
+        4 => OwnedDependency::LocalId {
+            name: r.string()?,
+            range: r.range()?,
+            explicit: r.u8()? != 0,
+        },
+        5 => OwnedDependency::LocalVar {
+            name: r.string()?,
+            range: r.range()?,
+            from: r.option()?,
+            from_range: r.option_range()?,
+        },
+        6 => OwnedDependency::LocalVarDecl {
+            name: r.string()?,
+            range: r.range()?,
+        },
+        7 => OwnedDependency::LocalPropertyDecl {
+            name: r.string()?,
+            range: r.range()?,
+        },
+        8 => OwnedDependency::LocalKeyframes {
+            name: r.string()?,
+            range: r.range()?,
+        },
+        9 => OwnedDependency::LocalKeyframesDecl {
+            name: r.string()?,
+            range: r.range()?,
+        },
+        10 => OwnedDependency::LocalCounterStyle {
+            name: r.string()?,
+            range: r.range()?,
+        },
+        11 => OwnedDependency::LocalCounterStyleDecl {
+            name: r.string()?,
+            range: r.range()?,
+        },
+        12 => OwnedDependency::LocalFontPalette {
+            name: r.string()?,
+            range: r.range()?,
+        },
+        13 => OwnedDependency::LocalFontPaletteDecl {
+            name: r.string()?,
+            range: r.range()?,
+        },
+        14 => OwnedDependency::Composes {
+            local_classes: r.strings()?,
+            names: r.strings()?,
+            from: r.option()?,
+            range: r.range()?,
+        },
+        15 => OwnedDependency::ICSSImportFrom { path: r.string()? },
+        16 => OwnedDependency::ICSSImportValue {
+            prop: r.string()?,
+            value: r.string()?,
+        },
+        17 => OwnedDependency::ICSSExportValue {
+            prop: r.string()?,
+            value: r.string()?,
+            range: r.range()?,
+        },
+        _ => return None,
+    })
+}
+
+fn write_warning_kind(w: &mut Writer, value: &OwnedWarningKind) {
+    match value {
+        OwnedWarningKind::Unexpected { message } => {
+            w.u8(0);
+            w.string(message);
+        }
+        OwnedWarningKind::DuplicateUrl { when } => {
+            w.u8(1);
+            w.string(when);
+        }
+        OwnedWarningKind::NamespaceNotSupportedInBundledCss => w.u8(2),
+        OwnedWarningKind::NotPrecededAtImport => w.u8(3),
+        OwnedWarningKind::ExpectedUrl { when } => {
+            w.u8(4);
+            w.string(when);
+        }
+        OwnedWarningKind::ExpectedUrlBefore { when } => {
+            w.u8(5);
+            w.string(when);
+        }
+        OwnedWarningKind::ExpectedLayerBefore { when } => {
+            w.u8(6);
+            w.string(when);
+        }
+        OwnedWarningKind::InconsistentModeResult => w.u8(7),
+        OwnedWarningKind::ExpectedNotInside { pseudo } => {
+            w.u8(8);
+            w.string(pseudo);
+        }
+        OwnedWarningKind::MissingWhitespace { surrounding } => {
+            w.u8(9);
+            w.string(surrounding);
+        }
+        OwnedWarningKind::NotPure { message } => {
+            w.u8(10);
+            w.string(message);
+        }
+        OwnedWarningKind::UnexpectedComposition { message } => {
+            w.u8(11);
+            w.string(message);
+        }
+        OwnedWarningKind::ExportNameCollision {
+            name,
+            other,
+            other_range,
+        } => {
+            w.u8(12);
+            w.string(name);
+            w.string(other);
+            w.range(other_range);
+        }
+        OwnedWarningKind::MissingComposedName { name, from } => {
+            w.u8(13);
+            w.string(name);
+            w.option(from.as_deref());
+        }
+        OwnedWarningKind::DependencyCycle { name, modules } => {
+            w.u8(14);
+            w.string(name);
+            w.varint(*modules as u64);
+        }
+        OwnedWarningKind::MissingAsset { request } => {
+            w.u8(15);
+            w.string(request);
+        }
+        OwnedWarningKind::MissingVar { name, from } => {
+            w.u8(16);
+            w.string(name);
+            w.string(from);
+        }
+        OwnedWarningKind::BadUrl => w.u8(17),
+        OwnedWarningKind::BadString => w.u8(18),
+        OwnedWarningKind::UnterminatedComment => w.u8(19),
+        OwnedWarningKind::UnclosedBlock { opening } => {
+            w.u8(20);
+            w.string(opening);
+        }
+        OwnedWarningKind::UnexpectedClosingBrace => w.u8(21),
+        OwnedWarningKind::MissingImport { request } => {
+            w.u8(22);
+            w.string(request);
+        }
+        OwnedWarningKind::InvalidEdit {
+            error: EditError::OutOfBounds { range },
+        } => {
+            w.u8(23);
+            w.range(range);
+        }
+        OwnedWarningKind::InvalidEdit {
+            error: EditError::Overlap { range, existing },
+        } => {
+            w.u8(24);
+            w.range(range);
+            w.range(existing);
+        }
+    }
+}
+
+fn read_warning_kind(r: &mut Reader) -> Option<OwnedWarningKind> {
+    Some(match r.u8()? {
+        0 => OwnedWarningKind::Unexpected {
+            message: r.string()?,
+        },
+        1 => OwnedWarningKind::DuplicateUrl { when: r.string()? },
+        2 => OwnedWarningKind::NamespaceNotSupportedInBundledCss,
+        3 => OwnedWarningKind::NotPrecededAtImport,
+        4 => OwnedWarningKind::ExpectedUrl { when: r.string()? },
+        5 => OwnedWarningKind::ExpectedUrlBefore { when: r.string()? },
+        6 => OwnedWarningKind::ExpectedLayerBefore { when: r.string()? },
+        7 => OwnedWarningKind::InconsistentModeResult,
+        8 => OwnedWarningKind::ExpectedNotInside {
+            pseudo: r.string()?,
+        },
+        9 => OwnedWarningKind::MissingWhitespace {
+            surrounding: r.string()?,
+        },
+        10 => OwnedWarningKind::NotPure {
+            message: r.string()?,
+        },
+        11 => OwnedWarningKind::UnexpectedComposition {
+            message: r.string()?,
+        },
+        12 => OwnedWarningKind::ExportNameCollision {
+            name: r.string()?,
+            other: r.string()?,
+            other_range: r.range()?,
+        },
+        13 => OwnedWarningKind::MissingComposedName {
+            name: r.string()?,
+            from: r.option()?,
+        },
+        14 => OwnedWarningKind::DependencyCycle {
+            name: r.string()?,
+            modules: r.varint()? as usize,
+        },
+        15 => OwnedWarningKind::MissingAsset {
+            request: r.string()?,
+        },
+        16 => OwnedWarningKind::MissingVar {
+            name: r.string()?,
+            from: r.string()?,
+        },
+        17 => OwnedWarningKind::BadUrl,
+        18 => OwnedWarningKind::BadString,
+        19 => OwnedWarningKind::UnterminatedComment,
+        20 => OwnedWarningKind::UnclosedBlock {
+            opening: r.string()?,
+        },
+        21 => OwnedWarningKind::UnexpectedClosingBrace,
+        22 => OwnedWarningKind::MissingImport {
+            request: r.string()?,
+        },
+        23 => OwnedWarningKind::InvalidEdit {
+            error: EditError::OutOfBounds { range: r.range()? },
+        },
+        24 => OwnedWarningKind::InvalidEdit {
+            error: EditError::Overlap {
+                range: r.range()?,
+                existing: r.range()?,
+            },
+        },
+        _ => return None,
+    })
+}
+
+fn mode_tag(mode: Mode) -> u8 {
+    match mode {
+        Mode::Local => 0,
+        Mode::Global => 1,
+        Mode::Pure => 2,
+        Mode::Css => 3,
+    }
+}
+
+fn header(w: &mut Writer, hash: &[u8; 32], mode: Mode) {
+    w.out.extend_from_slice(MAGIC);
+    w.u8(FORMAT);
+    w.string(VERSION);
+    w.u8(mode_tag(mode));
+    w.out.extend_from_slice(hash);
+}
+
+fn encode(
+    hash: &[u8; 32],
+    mode: Mode,
+    dependencies: &[OwnedDependency],
+    warnings: &[OwnedWarning],
+) -> Vec<u8> {
+    let mut w = Writer { out: Vec::new() };
+    header(&mut w, hash, mode);
+    w.varint(dependencies.len() as u64);
+    for dependency in dependencies {
+        write_dependency(&mut w, dependency);
+    }
+    w.varint(warnings.len() as u64);
+    for warning in warnings {
+        w.range(&warning.range);
+        write_warning_kind(&mut w, &warning.kind);
+    }
+    w.out
+}
+
+fn decode(
+    bytes: &[u8],
+    hash: &[u8; 32],
+    mode: Mode,
+) -> Option<(Vec<OwnedDependency>, Vec<OwnedWarning>)> {
+    let mut expected = Writer { out: Vec::new() };
+    header(&mut expected, hash, mode);
+    let mut r = Reader {
+        input: bytes.strip_prefix(expected.out.as_slice())?,
+    };
+    let len = r.varint()? as usize;
+    let mut dependencies = Vec::with_capacity(len.min(r.input.len()));
+    for _ in 0..len {
+        dependencies.push(read_dependency(&mut r)?);
+    }
+    let len = r.varint()? as usize;
+    let mut warnings = Vec::with_capacity(len.min(r.input.len()));
+    for _ in 0..len {
+        let range = r.range()?;
+        let kind = read_warning_kind(&mut r)?;
+        warnings.push(OwnedWarning { range, kind });
+    }
+    r.input.is_empty().then_some((dependencies, warnings))
+}
+
+// Entries are keyed by the SHA-256 of the content and the mode, the header also records the
+// format, the crate version and the whole hash so entries written by another version, or for
+// another input with the same file name, are misses.
+// Files are written to a temporary name and renamed into place, readers never see a partial
+// entry
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    // Named by the first 128 bits of the hash
+    fn path(&self, hash: &[u8; 32], mode: Mode) -> PathBuf {
+        let name = u128::from_be_bytes(hash[..16].try_into().unwrap());
+        self.dir.join(format!("{name:032x}-{}.bin", mode_tag(mode)))
+    }
+
+    pub fn get(
+        &self,
+        input: &str,
+        mode: Mode,
+    ) -> Option<(Vec<OwnedDependency>, Vec<OwnedWarning>)> {
+        self.read(&sha256(input.as_bytes()), mode)
+    }
+
+    fn read(
+        &self,
+        hash: &[u8; 32],
+        mode: Mode,
+    ) -> Option<(Vec<OwnedDependency>, Vec<OwnedWarning>)> {
+        let bytes = fs::read(self.path(hash, mode)).ok()?;
+        decode(&bytes, hash, mode)
+    }
+
+    pub fn put(
+        &self,
+        input: &str,
+        mode: Mode,
+        dependencies: &[OwnedDependency],
+        warnings: &[OwnedWarning],
+    ) -> io::Result<()> {
+        self.write(&sha256(input.as_bytes()), mode, dependencies, warnings)
+    }
+
+    fn write(
+        &self,
+        hash: &[u8; 32],
+        mode: Mode,
+        dependencies: &[OwnedDependency],
+        warnings: &[OwnedWarning],
+    ) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(hash, mode);
+        let temp = path.with_extension(format!(
+            "{}.{}.tmp",
+            process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp, encode(hash, mode, dependencies, warnings))?;
+        fs::rename(&temp, &path).inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })
+    }
+
+    // Failing to write the cache entry is not an error, the result is still returned
+    pub fn collect_dependencies(
+        &self,
+        input: &str,
+        mode: Mode,
+    ) -> (Vec<OwnedDependency>, Vec<OwnedWarning>) {
+        let hash = sha256(input.as_bytes());
+        if let Some(result) = self.read(&hash, mode) {
+            return result;
+        }
+        let (dependencies, warnings) = collect_dependencies(input, mode);
+        let dependencies: Vec<_> = dependencies.into_iter().map(|d| d.into_owned()).collect();
+        let warnings: Vec<_> = warnings.into_iter().map(|w| w.into_owned()).collect();
+        let _ = self.write(&hash, mode, &dependencies, &warnings);
+        (dependencies, warnings)
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::exports::unquote;
+use crate::Dependency;
+use crate::ModuleGraph;
+use crate::ModuleId;
+use crate::OwnedDependency;
+use crate::Warning;
+use crate::WarningKind;
+
+struct ComposesResolver<'g, G, W> {
+    graph: &'g ModuleGraph,
+    generate_local_name: G,
+    handle_warning: W,
+    resolved: HashMap<(ModuleId, &'g str), Vec<String>>,
+    resolving: Vec<(ModuleId, &'g str)>,
+}
+
+impl<'g, G, W> ComposesResolver<'g, G, W>
+where
+    G: FnMut(&'g str, &'g str) -> String,
+    W: FnMut(ModuleId, Warning<'g>),
+{
+    fn has_class(&self, module_id: ModuleId, name: &str) -> bool {
+        self.graph
+            .module(module_id)
+            .dependencies()
+            .iter()
+            .any(|d| matches!(d, OwnedDependency::LocalClass { name: n, .. } if n[1..] == *name))
+    }
+
+    fn resolve(&mut self, module_id: ModuleId, local: &'g str) -> Vec<String> {
+        if let Some(classes) = self.resolved.get(&(module_id, local)) {
+            return classes.clone();
+        }
+        let module = self.graph.module(module_id);
+        self.resolving.push((module_id, local));
+        let mut classes = vec![(self.generate_local_name)(module.id(), local)];
+        for (index, dependency) in module.dependencies().iter().enumerate() {
+            let Dependency::Composes {
+                local_classes,
+                names,
+                from,
+                range,
+            } = dependency.as_dependency()
+            else {
+                continue;
+            };
+            if !local_classes.contains(&local) {
+                continue;
+            }
+            let from = from.map(unquote);
+            let target = match from {
+                None => Some(module_id),
+                Some("global") => None,
+                Some(_) => module
+                    .edges()
+                    .iter()
+                    .find(|edge| edge.dependency == index)
+                    .and_then(|edge| edge.target),
+            };
+            for name in names {
+                let composed = match (from, target) {
+                    (Some("global"), _) => vec![name.to_string()],
+                    (_, Some(target)) if self.has_class(target, name) => {
+                        if let Some(position) =
+                            self.resolving.iter().position(|&r| r == (target, name))
+                        {
+                            let mut modules: Vec<ModuleId> = self.resolving[position..]
+                                .iter()
+                                .map(|&(module_id, _)| module_id)
+                                .collect();
+                            modules.sort();
+                            modules.dedup();
+                            (self.handle_warning)(
+                                module_id,
+                                Warning::new(
+                                    range.clone(),
+                                    WarningKind::DependencyCycle {
+                                        name,
+                                        modules: modules.len(),
+                                    },
+                                ),
+                            );
+                            continue;
+                        }
+                        self.resolve(target, name)
+                    }
+                    _ => {
+                        (self.handle_warning)(
+                            module_id,
+                            Warning::new(
+                                range.clone(),
+                                WarningKind::MissingComposedName { name, from },
+                            ),
+                        );
+                        continue;
+                    }
+                };
+                for class in composed {
+                    if !classes.contains(&class) {
+                        classes.push(class);
+                    }
+                }
+            }
+        }
+        self.resolving.pop();
+        self.resolved.insert((module_id, local), classes.clone());
+        classes
+    }
+}
+
+impl ModuleGraph {
+    // Each local class of the module mapped to its own generated name followed by every
+    // composed class, transitively and in declaration order
+    pub fn resolve_composes<'g>(
+        &'g self,
+        module_id: ModuleId,
+        generate_local_name: impl FnMut(&'g str, &'g str) -> String,
+        handle_warning: impl FnMut(ModuleId, Warning<'g>),
+    ) -> Vec<(&'g str, String)> {
+        let mut resolver = ComposesResolver {
+            graph: self,
+            generate_local_name,
+            handle_warning,
+            resolved: HashMap::new(),
+            resolving: Vec::new(),
+        };
+        let mut result: Vec<(&'g str, String)> = Vec::new();
+        for dependency in self.module(module_id).dependencies() {
+            let OwnedDependency::LocalClass { name, .. } = dependency else {
+                continue;
+            };
+            let local = &name[1..];
+            if result.iter().any(|(l, _)| *l == local) {
+                continue;
+            }
+            let classes = resolver.resolve(module_id, local);
+            result.push((local, classes.join(" ")));
+        }
+        result
+    }
+}
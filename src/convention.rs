@@ -0,0 +1,172 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use smallvec::smallvec;
+use smallvec::SmallVec;
+
+use crate::Dependency;
+use crate::HandleWarning;
+use crate::Range;
+use crate::Warning;
+use crate::WarningKind;
+
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ExportsConvention {
+    #[default]
+    AsIs,
+    CamelCase,
+    CamelCaseOnly,
+    Dashes,
+    DashesOnly,
+}
+
+impl ExportsConvention {
+    pub fn convert<'a>(&self, name: &'a str) -> SmallVec<[Cow<'a, str>; 2]> {
+        match self {
+            Self::AsIs => smallvec![Cow::Borrowed(name)],
+            Self::CamelCase => with_original(name, camel_case(name)),
+            Self::CamelCaseOnly => smallvec![camel_case(name)],
+            Self::Dashes => with_original(name, dashes_camel_case(name)),
+            Self::DashesOnly => smallvec![dashes_camel_case(name)],
+        }
+    }
+}
+
+fn with_original<'a>(name: &'a str, converted: Cow<'a, str>) -> SmallVec<[Cow<'a, str>; 2]> {
+    if converted == name {
+        smallvec![Cow::Borrowed(name)]
+    } else {
+        smallvec![Cow::Borrowed(name), converted]
+    }
+}
+
+fn is_camel_case_separator(c: char) -> bool {
+    c == '-' || c == '_'
+}
+
+pub fn camel_case(name: &str) -> Cow<'_, str> {
+    let trimmed = name.trim_start_matches(is_camel_case_separator);
+    if !trimmed.contains(is_camel_case_separator) {
+        return Cow::Borrowed(trimmed);
+    }
+    let mut result = String::with_capacity(trimmed.len());
+    let mut upper_next = false;
+    for c in trimmed.chars() {
+        if is_camel_case_separator(c) {
+            upper_next = true;
+        } else if upper_next {
+            result.extend(c.to_uppercase());
+            upper_next = false;
+        } else {
+            result.push(c);
+        }
+    }
+    Cow::Owned(result)
+}
+
+pub fn dashes_camel_case(name: &str) -> Cow<'_, str> {
+    if !name.contains('-') {
+        return Cow::Borrowed(name);
+    }
+    let mut result = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '-' {
+            result.push(c);
+            continue;
+        }
+        let mut dashes = 1;
+        while chars.peek() == Some(&'-') {
+            chars.next();
+            dashes += 1;
+        }
+        match chars.peek() {
+            Some(&next) if next.is_alphanumeric() || next == '_' => {
+                chars.next();
+                result.extend(next.to_uppercase());
+            }
+            _ => result.extend(std::iter::repeat('-').take(dashes)),
+        }
+    }
+    Cow::Owned(result)
+}
+
+impl<'s> Dependency<'s> {
+    pub fn local_export(&self) -> Option<(&'s str, &Range)> {
+        match self {
+            Dependency::LocalClass { name, range, .. }
+            | Dependency::LocalId { name, range, .. } => Some((&name[1..], range)),
+            Dependency::LocalKeyframesDecl { name, range }
+            | Dependency::LocalCounterStyleDecl { name, range } => Some((*name, range)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ExportNames<'s, W> {
+    convention: ExportsConvention,
+    // The local name behind each export name and whether it is an `:export` value
+    names: HashMap<Cow<'s, str>, (&'s str, Range, bool)>,
+    handle_warning: W,
+}
+
+impl<'s, W: HandleWarning<'s>> ExportNames<'s, W> {
+    pub fn new(convention: ExportsConvention, handle_warning: W) -> Self {
+        Self {
+            convention,
+            names: HashMap::new(),
+            handle_warning,
+        }
+    }
+
+    pub fn add(&mut self, name: &'s str, range: Range) -> SmallVec<[Cow<'s, str>; 2]> {
+        self.insert(name, range, false)
+    }
+
+    // An `:export` key collides with every other export of the same name, even a class of the
+    // same name
+    pub fn add_value(&mut self, name: &'s str, range: Range) -> SmallVec<[Cow<'s, str>; 2]> {
+        self.insert(name, range, true)
+    }
+
+    fn insert(&mut self, name: &'s str, range: Range, value: bool) -> SmallVec<[Cow<'s, str>; 2]> {
+        let converted = self.convention.convert(name);
+        for export_name in &converted {
+            match self.names.get(export_name) {
+                Some((other, other_range, other_value))
+                    if *other != name || value || *other_value =>
+                {
+                    self.handle_warning.handle_warning(Warning::new(
+                        range.clone(),
+                        WarningKind::ExportNameCollision {
+                            name,
+                            other,
+                            other_range: other_range.clone(),
+                        },
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    self.names
+                        .insert(export_name.clone(), (name, range.clone(), value));
+                }
+            }
+        }
+        converted
+    }
+
+    pub fn add_dependency(
+        &mut self,
+        dependency: &Dependency<'s>,
+    ) -> Option<SmallVec<[Cow<'s, str>; 2]>> {
+        let (name, range) = dependency.local_export()?;
+        Some(self.add(name, range.clone()))
+    }
+
+    pub fn get(&self, export_name: &str) -> Option<(&'s str, &Range)> {
+        self.names
+            .get(export_name)
+            .map(|(name, range, _)| (*name, range))
+    }
+}
@@ -16,9 +16,12 @@ use crate::lexer::C_RIGHT_CURLY;
 use crate::lexer::C_RIGHT_PARENTHESIS;
 use crate::lexer::C_SEMICOLON;
 use crate::lexer::C_SOLIDUS;
+use crate::EditError;
 use crate::HandleDependency;
 use crate::HandleWarning;
 use crate::Lexer;
+use crate::LineIndex;
+use crate::Location;
 use crate::Pos;
 
 #[derive(Debug)]
@@ -98,6 +101,10 @@ impl BalancedStack {
         self.0.is_empty()
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &BalancedItem> {
+        self.0.iter()
+    }
+
     pub fn push(&mut self, item: BalancedItem, mode_data: Option<&mut ModeData>) {
         if let Some(mode_data) = mode_data {
             if item.kind.is_mode_local() {
@@ -275,6 +282,14 @@ impl Range {
     pub fn new(start: Pos, end: Pos) -> Self {
         Self { start, end }
     }
+
+    pub fn location<'a>(&self, file: &'a str, index: &LineIndex) -> Location<'a> {
+        Location {
+            file,
+            start: index.line_col(self.start),
+            end: index.line_col(self.end),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
@@ -354,6 +369,15 @@ impl ModeData<'_> {
     pub fn is_mode_explicit(&self) -> bool {
         self.is_inside_mode_function() || self.is_inside_mode_class()
     }
+
+    // Nothing of a rule is left, except where the next selector starts
+    fn is_at_rest(&self) -> bool {
+        self.current == self.default
+            && self.property == self.default
+            && self.resulting_global.is_none()
+            && !self.is_mode_explicit()
+            && self.composes_local_classes.is_at_rest()
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -399,6 +423,11 @@ impl<'s> ComposesLocalClasses<'s> {
         self.local_classes.clear();
     }
 
+    // The classes found so far are slices of the input
+    pub fn is_at_rest(&self) -> bool {
+        self.local_classes.is_empty() && !matches!(self.is_single, SingleLocalClass::Single(_))
+    }
+
     pub fn reset_to_initial(&mut self) {
         self.is_single = SingleLocalClass::Initial;
         self.local_classes.clear();
@@ -416,8 +445,8 @@ impl<'s> ComposesLocalClasses<'s> {
     }
 }
 
-#[derive(Debug, Default, Clone)]
-enum SingleLocalClass {
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) enum SingleLocalClass {
     #[default]
     Initial,
     Single(Range),
@@ -658,6 +687,7 @@ pub enum Dependency<'s> {
         name: &'s str,
         range: Range,
         from: Option<&'s str>,
+        from_range: Option<Range>,
     },
     LocalVarDecl {
         name: &'s str,
@@ -707,9 +737,34 @@ pub enum Dependency<'s> {
     ICSSExportValue {
         prop: &'s str,
         value: &'s str,
+        range: Range,
     },
 }
 
+impl Dependency<'_> {
+    pub fn range(&self) -> Option<&Range> {
+        match self {
+            Dependency::Url { range, .. }
+            | Dependency::Import { range, .. }
+            | Dependency::Replace { range, .. }
+            | Dependency::LocalClass { range, .. }
+            | Dependency::LocalId { range, .. }
+            | Dependency::LocalVar { range, .. }
+            | Dependency::LocalVarDecl { range, .. }
+            | Dependency::LocalPropertyDecl { range, .. }
+            | Dependency::LocalKeyframes { range, .. }
+            | Dependency::LocalKeyframesDecl { range, .. }
+            | Dependency::LocalCounterStyle { range, .. }
+            | Dependency::LocalCounterStyleDecl { range, .. }
+            | Dependency::LocalFontPalette { range, .. }
+            | Dependency::LocalFontPaletteDecl { range, .. }
+            | Dependency::Composes { range, .. }
+            | Dependency::ICSSExportValue { range, .. } => Some(range),
+            Dependency::ICSSImportFrom { .. } | Dependency::ICSSImportValue { .. } => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum UrlRangeKind {
     Function,
@@ -734,22 +789,77 @@ impl<'s> Warning<'s> {
     pub fn kind(&self) -> &WarningKind<'s> {
         &self.kind
     }
+
+    pub fn location<'a>(&self, file: &'a str, index: &LineIndex) -> Location<'a> {
+        self.range.location(file, index)
+    }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum WarningKind<'s> {
-    Unexpected { message: &'s str },
-    DuplicateUrl { when: &'s str },
+    Unexpected {
+        message: &'s str,
+    },
+    DuplicateUrl {
+        when: &'s str,
+    },
     NamespaceNotSupportedInBundledCss,
     NotPrecededAtImport,
-    ExpectedUrl { when: &'s str },
-    ExpectedUrlBefore { when: &'s str },
-    ExpectedLayerBefore { when: &'s str },
+    ExpectedUrl {
+        when: &'s str,
+    },
+    ExpectedUrlBefore {
+        when: &'s str,
+    },
+    ExpectedLayerBefore {
+        when: &'s str,
+    },
     InconsistentModeResult,
-    ExpectedNotInside { pseudo: &'s str },
-    MissingWhitespace { surrounding: &'s str },
-    NotPure { message: &'s str },
-    UnexpectedComposition { message: &'s str },
+    ExpectedNotInside {
+        pseudo: &'s str,
+    },
+    MissingWhitespace {
+        surrounding: &'s str,
+    },
+    NotPure {
+        message: &'s str,
+    },
+    UnexpectedComposition {
+        message: &'s str,
+    },
+    ExportNameCollision {
+        name: &'s str,
+        other: &'s str,
+        other_range: Range,
+    },
+    MissingComposedName {
+        name: &'s str,
+        from: Option<&'s str>,
+    },
+    DependencyCycle {
+        name: &'s str,
+        modules: usize,
+    },
+    MissingImport {
+        request: &'s str,
+    },
+    InvalidEdit {
+        error: EditError,
+    },
+    MissingAsset {
+        request: &'s str,
+    },
+    MissingVar {
+        name: &'s str,
+        from: &'s str,
+    },
+    BadUrl,
+    BadString,
+    UnterminatedComment,
+    UnclosedBlock {
+        opening: &'s str,
+    },
+    UnexpectedClosingBrace,
 }
 
 impl Display for Warning<'_> {
@@ -790,23 +900,129 @@ impl Display for Warning<'_> {
             ),
             WarningKind::NotPure { message, .. } => write!(f, "Pure globals is not allowed in pure mode, {message}"),
             WarningKind::UnexpectedComposition {  message, .. } => write!(f, "Composition is {message}"),
+            WarningKind::ExportNameCollision { name, other, .. } => write!(
+                f,
+                "Export name of '{name}' collides with the export name of '{other}'"
+            ),
+            WarningKind::MissingComposedName {
+                name,
+                from: Some(from),
+            } => write!(f, "Composed class '{name}' is not defined in '{from}'"),
+            WarningKind::MissingComposedName { name, from: None } => {
+                write!(f, "Composed class '{name}' is not defined")
+            }
+            WarningKind::DependencyCycle { name, modules: 1 } => {
+                write!(f, "Dependency on '{name}' is circular")
+            }
+            WarningKind::DependencyCycle { name, modules } => write!(
+                f,
+                "Dependency on '{name}' is circular through {modules} modules"
+            ),
+            WarningKind::MissingImport { request } => write!(
+                f,
+                "Can't resolve '{request}', it is kept as an external import"
+            ),
+            WarningKind::InvalidEdit { ref error } => {
+                write!(f, "{error}, the source is kept as it is")
+            }
+            WarningKind::MissingAsset { request } => write!(f, "Can't resolve '{request}'"),
+            WarningKind::MissingVar { name, from } => {
+                write!(f, "Custom property '--{name}' is not declared in '{from}'")
+            }
+            WarningKind::BadUrl => write!(
+                f,
+                "Unquoted 'url(...)' can't contain whitespace or '(', quote the url instead"
+            ),
+            WarningKind::BadString => write!(f, "Unterminated string, newlines must be escaped"),
+            WarningKind::UnterminatedComment => write!(f, "Unterminated comment"),
+            WarningKind::UnclosedBlock { opening } => write!(f, "'{opening}' is never closed"),
+            WarningKind::UnexpectedClosingBrace => {
+                write!(f, "Unexpected '}}' without a matching '{{'")
+            }
         }
     }
 }
 
+// What a rule leaves behind for the rules after it, positions are relative to the input it was
+// taken from. `open_blocks` are the blocks the rule is in, like `@media` or `@layer` blocks, a rule
+// in them may end `in_block` with `block_nesting_level` above 0. With mode data,
+// `single_local_class` tells whether the next selector can still be a single class for `composes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SplitState {
+    pub allow_import_at_rule: bool,
+    pub pure_global: Option<Pos>,
+    pub open_blocks: SmallVec<[Pos; 4]>,
+    pub block_nesting_level: u32,
+    pub in_block: bool,
+    pub is_next_rule_prelude: bool,
+    pub single_local_class: Option<SingleLocalClass>,
+}
+
+// The end of a rule, lexing can resume there with `state`. `dependencies` and `warnings` count
+// what was reported before it
+#[derive(Debug, Clone)]
+pub(crate) struct SplitPoint {
+    pub end: Pos,
+    pub state: SplitState,
+    pub dependencies: usize,
+    pub warnings: usize,
+}
+
+// Used to lex the input in parts
+#[derive(Debug, Default)]
+struct Split {
+    // Split points are recorded up to `limit`
+    limit: Option<Pos>,
+    points: Vec<SplitPoint>,
+    // Lexing stops at the first split point matching one of these, sorted by position
+    stops: Vec<(Pos, SplitState)>,
+    stopped: bool,
+    // How far the reported ranges reach ahead, a bad url reaches the next `)`
+    reported_end: Pos,
+}
+
+// Counts what was handled, so split points know what was reported before them
+#[derive(Debug)]
+struct Counted<T> {
+    handler: T,
+    count: usize,
+}
+
+impl<T> Counted<T> {
+    fn new(handler: T) -> Self {
+        Self { handler, count: 0 }
+    }
+}
+
+impl<'s, D: HandleDependency<'s>> HandleDependency<'s> for Counted<D> {
+    fn handle_dependency(&mut self, dependency: Dependency<'s>) {
+        self.count += 1;
+        self.handler.handle_dependency(dependency);
+    }
+}
+
+impl<'s, W: HandleWarning<'s>> HandleWarning<'s> for Counted<W> {
+    fn handle_warning(&mut self, warning: Warning<'s>) {
+        self.count += 1;
+        self.handler.handle_warning(warning);
+    }
+}
+
 #[derive(Debug)]
 pub struct LexDependencies<'s, D, W> {
     mode_data: Option<ModeData<'s>>,
     scope: Scope<'s>,
     block_nesting_level: u32,
+    open_blocks: SmallVec<[Pos; 4]>,
     allow_import_at_rule: bool,
     balanced: BalancedStack,
     is_next_rule_prelude: bool,
     in_animation_property: Option<InProperty<AnimationReserved>>,
     in_list_style_property: Option<InProperty<ListStyleReserved>>,
     in_font_palette_property: Option<InProperty<FontPaletteReserved>>,
-    handle_dependency: D,
-    handle_warning: W,
+    split: Split,
+    handle_dependency: Counted<D>,
+    handle_warning: Counted<W>,
 }
 
 impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W> {
@@ -819,19 +1035,114 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
             },
             scope: Scope::TopLevel,
             block_nesting_level: 0,
+            open_blocks: SmallVec::new(),
             allow_import_at_rule: true,
             balanced: Default::default(),
             is_next_rule_prelude: true,
             in_animation_property: None,
             in_list_style_property: None,
             in_font_palette_property: None,
-            handle_dependency,
-            handle_warning,
+            split: Split::default(),
+            handle_dependency: Counted::new(handle_dependency),
+            handle_warning: Counted::new(handle_warning),
         }
     }
 
-    fn is_next_nested_syntax(&self, lexer: &mut Lexer) -> Option<bool> {
-        lexer.consume_white_space_and_comments()?;
+    pub(crate) fn split_state(&self) -> SplitState {
+        SplitState {
+            allow_import_at_rule: self.allow_import_at_rule,
+            pure_global: self
+                .mode_data
+                .as_ref()
+                .and_then(|mode_data| mode_data.pure_global),
+            open_blocks: self.open_blocks.clone(),
+            block_nesting_level: self.block_nesting_level,
+            in_block: matches!(self.scope, Scope::InBlock),
+            is_next_rule_prelude: self.is_next_rule_prelude,
+            single_local_class: self
+                .mode_data
+                .as_ref()
+                .map(|mode_data| mode_data.composes_local_classes.is_single.clone()),
+        }
+    }
+
+    pub(crate) fn set_split_state(&mut self, state: SplitState) {
+        self.allow_import_at_rule = state.allow_import_at_rule;
+        if let Some(mode_data) = &mut self.mode_data {
+            mode_data.pure_global = state.pure_global;
+            if let Some(is_single) = state.single_local_class {
+                mode_data.composes_local_classes.is_single = is_single;
+            }
+        }
+        self.open_blocks = state.open_blocks;
+        self.block_nesting_level = state.block_nesting_level;
+        if state.in_block {
+            self.scope = Scope::InBlock;
+        }
+        self.is_next_rule_prelude = state.is_next_rule_prelude;
+    }
+
+    pub(crate) fn record_splits(&mut self, limit: Pos) {
+        self.split.limit = Some(limit);
+    }
+
+    pub(crate) fn stop_at_splits(&mut self, stops: Vec<(Pos, SplitState)>) {
+        self.split.stops = stops;
+    }
+
+    // The recorded split points and whether lexing stopped at the last one
+    pub(crate) fn into_splits(self) -> (Vec<SplitPoint>, bool) {
+        (self.split.points, self.split.stopped)
+    }
+
+    fn split_point(&mut self, end: Pos) -> Option<()> {
+        if self.split.reported_end > end
+            || !self.balanced.is_empty()
+            || self.in_animation_property.is_some()
+            || self.in_list_style_property.is_some()
+            || self.in_font_palette_property.is_some()
+            || !self.mode_data.as_ref().map_or(true, ModeData::is_at_rest)
+        {
+            return Some(());
+        }
+        let state = self.split_state();
+        let stop = self
+            .split
+            .stops
+            .binary_search_by_key(&end, |(pos, _)| *pos)
+            .is_ok_and(|i| self.split.stops[i].1 == state);
+        if stop || self.split.limit.is_some_and(|limit| end <= limit) {
+            self.split.points.push(SplitPoint {
+                end,
+                state,
+                dependencies: self.handle_dependency.count,
+                warnings: self.handle_warning.count,
+            });
+        }
+        if stop {
+            self.split.stopped = true;
+            return None;
+        }
+        Some(())
+    }
+
+    // Skips white space and comments, a comment that is never closed is reported as if it was
+    // lexed
+    fn consume_white_space_and_comments(&mut self, lexer: &mut Lexer<'s>) -> Option<()> {
+        loop {
+            let c = lexer.cur()?;
+            if c == C_SOLIDUS && lexer.peek() == Some(C_ASTERISK) {
+                lexer.consume_comment(self)?;
+            } else if is_white_space(c) {
+                lexer.consume_space()?;
+            } else {
+                return Some(());
+            }
+        }
+    }
+
+    fn is_next_nested_syntax(&mut self, lexer: &mut Lexer<'s>) -> Option<bool> {
+        self.consume_white_space_and_comments(lexer)?;
         let c = lexer.cur()?;
         if c == C_RIGHT_CURLY {
             return Some(false);
@@ -918,11 +1229,13 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
         has_white_space
     }
 
-    fn has_after_white_space(&self, lexer: &mut Lexer<'s>) -> Option<bool> {
+    fn has_after_white_space(&mut self, lexer: &mut Lexer<'s>) -> Option<bool> {
         let mut has_white_space = false;
         loop {
-            lexer.consume_comments()?;
-            if is_white_space(lexer.cur()?) {
+            let c = lexer.cur()?;
+            if c == C_SOLIDUS && lexer.peek() == Some(C_ASTERISK) {
+                lexer.consume_comment(self)?;
+            } else if is_white_space(c) {
                 has_white_space = true;
                 lexer.consume_space()?;
             } else {
@@ -945,7 +1258,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
     }
 
     fn lex_icss_import(&mut self, lexer: &mut Lexer<'s>) -> Option<()> {
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         let start = lexer.cur_pos()?;
         loop {
             let c = lexer.cur()?;
@@ -960,7 +1273,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
                 path: lexer.slice(start, end)?,
             });
         lexer.consume();
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         if !self.eat(
             lexer,
             &[C_LEFT_CURLY],
@@ -968,13 +1281,13 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
         )? {
             return Some(());
         }
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         while lexer.cur()? != C_RIGHT_CURLY {
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
             let prop_start = lexer.cur_pos()?;
             self.consume_icss_export_prop(lexer)?;
             let prop_end = lexer.cur_pos()?;
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
             if !self.eat(
                 lexer,
                 &[C_COLON],
@@ -982,13 +1295,13 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
             )? {
                 return Some(());
             }
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
             let value_start = lexer.cur_pos()?;
             self.consume_icss_export_value(lexer)?;
             let value_end = lexer.cur_pos()?;
             if lexer.cur()? == C_SEMICOLON {
                 lexer.consume();
-                lexer.consume_white_space_and_comments()?;
+                self.consume_white_space_and_comments(lexer)?;
             }
             self.handle_dependency
                 .handle_dependency(Dependency::ICSSImportValue {
@@ -1031,7 +1344,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
     }
 
     fn lex_icss_export(&mut self, lexer: &mut Lexer<'s>) -> Option<()> {
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         if !self.eat(
             lexer,
             &[C_LEFT_CURLY],
@@ -1039,13 +1352,13 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
         )? {
             return Some(());
         }
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         while lexer.cur()? != C_RIGHT_CURLY {
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
             let prop_start = lexer.cur_pos()?;
             self.consume_icss_export_prop(lexer)?;
             let prop_end = lexer.cur_pos()?;
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
             if !self.eat(
                 lexer,
                 &[C_COLON],
@@ -1053,22 +1366,24 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
             )? {
                 return Some(());
             }
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
             let value_start = lexer.cur_pos()?;
             self.consume_icss_export_value(lexer)?;
             let value_end = lexer.cur_pos()?;
             if lexer.cur()? == C_SEMICOLON {
                 lexer.consume();
-                lexer.consume_white_space_and_comments()?;
+                self.consume_white_space_and_comments(lexer)?;
             }
+            let prop = lexer
+                .slice(prop_start, prop_end)?
+                .trim_end_matches(is_white_space);
             self.handle_dependency
                 .handle_dependency(Dependency::ICSSExportValue {
-                    prop: lexer
-                        .slice(prop_start, prop_end)?
-                        .trim_end_matches(is_white_space),
+                    prop,
                     value: lexer
                         .slice(value_start, value_end)?
                         .trim_end_matches(is_white_space),
+                    range: Range::new(prop_start, prop_start + prop.len() as Pos),
                 });
         }
         lexer.consume();
@@ -1076,7 +1391,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
     }
 
     fn lex_local_var(&mut self, lexer: &mut Lexer<'s>) -> Option<()> {
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         let start = lexer.cur_pos()?;
         if lexer.cur()? != C_HYPHEN_MINUS || lexer.peek()? != C_HYPHEN_MINUS {
             self.handle_warning.handle_warning(Warning {
@@ -1090,14 +1405,15 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
         lexer.consume_ident_sequence()?;
         let name_start = start + 2;
         let end = lexer.cur_pos()?;
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         let from_start = lexer.cur_pos()?;
-        let from = if matches!(lexer.slice(from_start, from_start + 4), Some("from")) {
+        let (from, from_range) = if matches!(lexer.slice(from_start, from_start + 4), Some("from"))
+        {
             lexer.consume();
             lexer.consume();
             lexer.consume();
             lexer.consume();
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
             let c = lexer.cur()?;
             let path_start = lexer.cur_pos()?;
             if c == '\'' || c == '"' {
@@ -1114,15 +1430,20 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
                 });
                 return Some(());
             }
-            Some(lexer.slice(path_start, lexer.cur_pos()?)?)
+            let path_end = lexer.cur_pos()?;
+            (
+                Some(lexer.slice(path_start, path_end)?),
+                Some(Range::new(path_start, path_end)),
+            )
         } else {
-            None
+            (None, None)
         };
         self.handle_dependency
             .handle_dependency(Dependency::LocalVar {
                 name: lexer.slice(name_start, end)?,
                 range: Range::new(start, end),
                 from,
+                from_range,
             });
         Some(())
     }
@@ -1134,7 +1455,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
         start: Pos,
         end: Pos,
     ) -> Option<()> {
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         if lexer.cur()? != C_COLON {
             return Some(());
         }
@@ -1154,7 +1475,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
         dashed_warning: impl FnOnce(Range) -> Warning<'s>,
         left_curly_warning: impl FnOnce(Range) -> Warning<'s>,
     ) -> Option<()> {
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         let start = lexer.cur_pos()?;
         if lexer.cur()? != C_HYPHEN_MINUS || lexer.peek()? != C_HYPHEN_MINUS {
             self.handle_warning
@@ -1169,7 +1490,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
                 lexer.slice(name_start, end)?,
                 Range::new(start, end),
             ));
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         if lexer.cur()? != C_LEFT_CURLY {
             self.handle_warning
                 .handle_warning(left_curly_warning(Range::new(
@@ -1182,7 +1503,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
     }
 
     fn lex_local_keyframes_decl(&mut self, lexer: &mut Lexer<'s>) -> Option<()> {
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         let mut is_function = false;
         if lexer.cur()? == C_COLON {
             let start = lexer.cur_pos()?;
@@ -1214,7 +1535,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
                 });
                 return Some(());
             }
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
         }
         let start = lexer.cur_pos()?;
         if !start_ident_sequence(lexer.cur()?, lexer.peek()?, lexer.peek2()?) {
@@ -1236,7 +1557,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
                     range: Range::new(start, end),
                 });
         }
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         if is_function {
             if lexer.cur()? != C_RIGHT_PARENTHESIS {
                 self.handle_warning.handle_warning(Warning {
@@ -1252,10 +1573,10 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
                     content: "",
                     range: Range::new(lexer.cur_pos()?, lexer.peek_pos()?),
                 });
-            mode_data.inside_mode_function -= 1;
+            self.mode_data.as_mut().unwrap().inside_mode_function -= 1;
             self.balanced.pop_without_moda_data();
             lexer.consume();
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
         }
         if lexer.cur()? != C_LEFT_CURLY {
             self.handle_warning.handle_warning(Warning {
@@ -1283,7 +1604,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
     }
 
     fn lex_local_counter_style_decl(&mut self, lexer: &mut Lexer<'s>) -> Option<()> {
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         let start = lexer.cur_pos()?;
         if !start_ident_sequence(lexer.cur()?, lexer.peek()?, lexer.peek2()?) {
             self.handle_warning.handle_warning(Warning {
@@ -1301,7 +1622,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
                 name: lexer.slice(start, end)?,
                 range: Range::new(start, end),
             });
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         if lexer.cur()? != C_LEFT_CURLY {
             self.handle_warning.handle_warning(Warning {
                 range: Range::new(lexer.cur_pos()?, lexer.peek_pos()?),
@@ -1344,7 +1665,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
         local_classes: SmallVec<[&'s str; 2]>,
         start: Pos,
     ) -> Option<()> {
-        lexer.consume_white_space_and_comments()?;
+        self.consume_white_space_and_comments(lexer)?;
         if lexer.cur()? != C_COLON {
             return Some(());
         }
@@ -1353,7 +1674,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
         let mut end;
         let mut has_from = false;
         loop {
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
             let start = lexer.cur_pos()?;
             end = start;
             loop {
@@ -1381,7 +1702,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
                     }
                     lexer.consume_ident_sequence()?;
                     let name_end = lexer.cur_pos()?;
-                    lexer.consume_white_space_and_comments()?;
+                    self.consume_white_space_and_comments(lexer)?;
                     self.eat(
                         lexer,
                         &[C_RIGHT_PARENTHESIS],
@@ -1418,9 +1739,9 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
                     names.push(lexer.slice(name_start, name_end)?);
                     end = name_end;
                 }
-                lexer.consume_white_space_and_comments()?;
+                self.consume_white_space_and_comments(lexer)?;
             }
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
             let c = lexer.cur()?;
             if !has_from {
                 if !names.is_empty() {
@@ -1463,7 +1784,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> LexDependencies<'s, D, W
                     from,
                     range: Range::new(start, end),
                 });
-            lexer.consume_white_space_and_comments()?;
+            self.consume_white_space_and_comments(lexer)?;
             if lexer.cur()? != C_COMMA {
                 break;
             }
@@ -1808,7 +2129,10 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> Visitor<'s> for LexDepen
             let mut is_function = last.kind.is_mode_function();
             if last.kind.is_mode_class() {
                 self.balanced.pop_mode_pseudo_class(mode_data);
-                let popped = self.balanced.pop_without_moda_data().unwrap();
+                // A `)` without a matching `(`, like the empty stack above
+                let Some(popped) = self.balanced.pop_without_moda_data() else {
+                    return Some(());
+                };
                 debug_assert!(!matches!(
                     popped.kind,
                     BalancedItemKind::GlobalClass | BalancedItemKind::LocalClass
@@ -2028,7 +2352,8 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> Visitor<'s> for LexDepen
         Some(())
     }
 
-    fn left_curly_bracket(&mut self, lexer: &mut Lexer, start: Pos, _: Pos) -> Option<()> {
+    fn left_curly_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, _: Pos) -> Option<()> {
+        self.open_blocks.push(start);
         match self.scope {
             Scope::TopLevel => {
                 self.allow_import_at_rule = false;
@@ -2071,16 +2396,22 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> Visitor<'s> for LexDepen
                 let mode_data = self.mode_data.as_mut().unwrap();
                 mode_data.composes_local_classes.reset_to_initial();
             }
-
-            debug_assert!(
-                self.balanced.is_empty(),
-                "balanced should be empty when end of selector"
-            );
         }
         Some(())
     }
 
-    fn right_curly_bracket(&mut self, lexer: &mut Lexer<'s>, _: Pos, end: Pos) -> Option<()> {
+    fn right_curly_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        if self.open_blocks.pop().is_none() {
+            self.handle_warning.handle_warning(Warning {
+                range: Range::new(start, end),
+                kind: WarningKind::UnexpectedClosingBrace,
+            });
+        }
+        // The end of a block like `@media` around rules, with mode data its rules end at the top
+        // level scope
+        if matches!(self.scope, Scope::TopLevel) {
+            return self.split_point(end);
+        }
         if matches!(self.scope, Scope::InBlock) {
             if let Some(mode_data) = &mut self.mode_data {
                 mode_data.pure_global = Some(end);
@@ -2110,8 +2441,12 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> Visitor<'s> for LexDepen
                 if let Some(mode_data) = &mut self.mode_data {
                     mode_data.composes_local_classes.reset_to_initial();
                 }
+                self.split_point(end)?;
             } else {
                 self.is_next_rule_prelude = self.is_next_nested_syntax(lexer)?;
+                // A rule in nested blocks like `@media` and `@layer`, lexing can resume after what
+                // was looked at to tell whether a rule follows
+                self.split_point(lexer.cur_pos()?)?;
             }
         }
         Some(())
@@ -2139,7 +2474,7 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> Visitor<'s> for LexDepen
                     });
                 }
 
-                lexer.consume_white_space_and_comments()?;
+                self.consume_white_space_and_comments(lexer)?;
                 self.handle_dependency
                     .handle_dependency(Dependency::Replace {
                         content: "",
@@ -2266,4 +2601,63 @@ impl<'s, D: HandleDependency<'s>, W: HandleWarning<'s>> Visitor<'s> for LexDepen
 
         Some(())
     }
+
+    fn bad_url(&mut self, _: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.split.reported_end = self.split.reported_end.max(end);
+        self.handle_warning.handle_warning(Warning {
+            range: Range::new(start, end),
+            kind: WarningKind::BadUrl,
+        });
+        Some(())
+    }
+
+    fn bad_string(&mut self, _: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.handle_warning.handle_warning(Warning {
+            range: Range::new(start, end),
+            kind: WarningKind::BadString,
+        });
+        Some(())
+    }
+
+    fn unterminated_comment(&mut self, _: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.handle_warning.handle_warning(Warning {
+            range: Range::new(start, end),
+            kind: WarningKind::UnterminatedComment,
+        });
+        Some(())
+    }
+
+    fn eof(&mut self, lexer: &mut Lexer<'s>, _: Pos) -> Option<()> {
+        // Lexing was stopped by a callback or at a split point, the blocks may still be closed
+        // later in the input
+        if lexer.cur().is_some() || self.split.stopped {
+            return Some(());
+        }
+        // Blocks consumed while lexing `:import` and `:export` are never pushed here. An open block
+        // may start before the lexed input, when it's lexed in parts
+        let mut unclosed: SmallVec<[(Range, &'s str); 4]> = self
+            .open_blocks
+            .iter()
+            .map(|&start| Some((Range::new(start, start + 1), "{")))
+            .chain(
+                self.balanced
+                    .iter()
+                    .filter(|item| !item.kind.is_mode_class())
+                    .map(|item| {
+                        Some((
+                            item.range.clone(),
+                            lexer.slice(item.range.start, item.range.end)?,
+                        ))
+                    }),
+            )
+            .collect::<Option<_>>()?;
+        unclosed.sort();
+        for (range, opening) in unclosed {
+            self.handle_warning.handle_warning(Warning {
+                kind: WarningKind::UnclosedBlock { opening },
+                range,
+            });
+        }
+        Some(())
+    }
 }
@@ -0,0 +1,317 @@
+use std::borrow::Cow;
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::io;
+
+use crate::source_map::LineCol;
+use crate::source_map::SourceMap;
+use crate::source_map::SourceMapBuilder;
+use crate::Pos;
+use crate::Range;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Replacement<'a> {
+    pub range: Range,
+    pub content: Cow<'a, str>,
+    pub name: Option<&'a str>,
+}
+
+impl<'a> Replacement<'a> {
+    pub fn new(range: Range, content: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            range,
+            content: content.into(),
+            name: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+}
+
+struct SourceMapWriter<'a> {
+    builder: SourceMapBuilder,
+    source: u32,
+    input: &'a str,
+    original: LineCol,
+    original_pos: usize,
+    generated: LineCol,
+}
+
+impl<'a> SourceMapWriter<'a> {
+    fn new(input: &'a str, source_name: &str) -> Self {
+        let mut builder = SourceMapBuilder::new(None);
+        let source = builder.add_source(source_name, Some(input));
+        Self {
+            builder,
+            source,
+            input,
+            original: LineCol::default(),
+            original_pos: 0,
+            generated: LineCol::default(),
+        }
+    }
+
+    fn seek_original(&mut self, pos: usize) {
+        self.original.advance(&self.input[self.original_pos..pos]);
+        self.original_pos = pos;
+    }
+
+    fn copy(&mut self, start: usize, end: usize) {
+        self.seek_original(start);
+        let segment = &self.input[start..end];
+        let mut rest = segment;
+        loop {
+            if rest.is_empty() {
+                break;
+            }
+            self.builder
+                .add_mapping(self.generated, self.source, self.original, None);
+            let line_end = rest
+                .find(['\n', '\r', '\u{c}'])
+                .map(|i| {
+                    if rest[i..].starts_with("\r\n") {
+                        i + 2
+                    } else {
+                        i + 1
+                    }
+                })
+                .unwrap_or(rest.len());
+            let (line, next) = rest.split_at(line_end);
+            self.generated.advance(line);
+            self.original.advance(line);
+            rest = next;
+        }
+        self.original_pos = end;
+    }
+
+    fn replace(&mut self, replacement: &Replacement) {
+        let start = replacement.range.start as usize;
+        self.seek_original(start);
+        if !replacement.content.is_empty() {
+            let name = replacement.name.map(|name| self.builder.add_name(name));
+            self.builder
+                .add_mapping(self.generated, self.source, self.original, name);
+            self.generated.advance(&replacement.content);
+        }
+        self.seek_original(replacement.range.end as usize);
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum EditError {
+    OutOfBounds { range: Range },
+    Overlap { range: Range, existing: Range },
+}
+
+impl Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditError::OutOfBounds { range } => {
+                write!(f, "Edit {}..{} is out of bounds", range.start, range.end)
+            }
+            EditError::Overlap { range, existing } => write!(
+                f,
+                "Edit {}..{} overlaps with the existing edit {}..{}",
+                range.start, range.end, existing.start, existing.end
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+enum Chunk<'a> {
+    Original(usize, usize),
+    Edit(&'a Replacement<'a>),
+    Generated(&'a str),
+}
+
+#[derive(Debug, Clone)]
+pub struct EditBuffer<'s> {
+    input: &'s str,
+    intro: String,
+    outro: String,
+    edits: Vec<Replacement<'s>>,
+}
+
+impl<'s> EditBuffer<'s> {
+    pub fn new(input: &'s str) -> Self {
+        Self {
+            input,
+            intro: String::new(),
+            outro: String::new(),
+            edits: Vec::new(),
+        }
+    }
+
+    pub fn input(&self) -> &'s str {
+        self.input
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty() && self.intro.is_empty() && self.outro.is_empty()
+    }
+
+    pub fn prepend(&mut self, content: &str) {
+        self.intro.insert_str(0, content);
+    }
+
+    pub fn append(&mut self, content: &str) {
+        self.outro += content;
+    }
+
+    pub fn insert(&mut self, pos: Pos, content: impl Into<Cow<'s, str>>) -> Result<(), EditError> {
+        self.edit(Replacement::new(Range::new(pos, pos), content))
+    }
+
+    pub fn replace(
+        &mut self,
+        range: Range,
+        content: impl Into<Cow<'s, str>>,
+    ) -> Result<(), EditError> {
+        self.edit(Replacement::new(range, content))
+    }
+
+    pub fn remove(&mut self, range: Range) -> Result<(), EditError> {
+        self.edit(Replacement::new(range, ""))
+    }
+
+    // Edits with the same start are ordered by their end, then by the order they were added,
+    // so insertions at a position always come before a replacement starting at it
+    pub fn edit(&mut self, replacement: Replacement<'s>) -> Result<(), EditError> {
+        let range = &replacement.range;
+        if range.start > range.end
+            || !self.input.is_char_boundary(range.start as usize)
+            || !self.input.is_char_boundary(range.end as usize)
+        {
+            return Err(EditError::OutOfBounds {
+                range: range.clone(),
+            });
+        }
+        let overlaps = |existing: &Range| {
+            if range.start == range.end {
+                existing.start < range.start && range.start < existing.end
+            } else if existing.start == existing.end {
+                range.start < existing.start && existing.start < range.end
+            } else {
+                range.start.max(existing.start) < range.end.min(existing.end)
+            }
+        };
+        let index = self
+            .edits
+            .partition_point(|e| (e.range.start, e.range.end) <= (range.start, range.end));
+        for existing in self.edits[..index].iter().rev() {
+            if overlaps(&existing.range) {
+                return Err(EditError::Overlap {
+                    range: range.clone(),
+                    existing: existing.range.clone(),
+                });
+            }
+            if existing.range.end <= range.start && existing.range.start < range.start {
+                break;
+            }
+        }
+        for existing in &self.edits[index..] {
+            if existing.range.start >= range.end {
+                break;
+            }
+            if overlaps(&existing.range) {
+                return Err(EditError::Overlap {
+                    range: range.clone(),
+                    existing: existing.range.clone(),
+                });
+            }
+        }
+        self.edits.insert(index, replacement);
+        Ok(())
+    }
+
+    fn visit<E>(&self, mut f: impl FnMut(Chunk) -> Result<(), E>) -> Result<(), E> {
+        if !self.intro.is_empty() {
+            f(Chunk::Generated(&self.intro))?;
+        }
+        let mut index = 0;
+        for edit in &self.edits {
+            let start = edit.range.start as usize;
+            if index < start {
+                f(Chunk::Original(index, start))?;
+            }
+            f(Chunk::Edit(edit))?;
+            index = index.max(edit.range.end as usize);
+        }
+        if index < self.input.len() {
+            f(Chunk::Original(index, self.input.len()))?;
+        }
+        if !self.outro.is_empty() {
+            f(Chunk::Generated(&self.outro))?;
+        }
+        Ok(())
+    }
+
+    pub fn write_to(&self, w: &mut impl io::Write) -> io::Result<()> {
+        self.visit(|chunk| match chunk {
+            Chunk::Original(start, end) => w.write_all(self.input[start..end].as_bytes()),
+            Chunk::Edit(edit) => w.write_all(edit.content.as_bytes()),
+            Chunk::Generated(content) => w.write_all(content.as_bytes()),
+        })
+    }
+
+    pub fn to_string_with_source_map(&self, source_name: &str) -> (String, SourceMap) {
+        let mut result = String::with_capacity(self.input.len());
+        let mut writer = SourceMapWriter::new(self.input, source_name);
+        let _ = self.visit(|chunk| {
+            match chunk {
+                Chunk::Original(start, end) => {
+                    writer.copy(start, end);
+                    result += &self.input[start..end];
+                }
+                Chunk::Edit(edit) => {
+                    writer.replace(edit);
+                    result += &edit.content;
+                }
+                Chunk::Generated(content) => {
+                    writer.generated.advance(content);
+                    result += content;
+                }
+            }
+            Ok::<_, Infallible>(())
+        });
+        (result, writer.builder.build())
+    }
+}
+
+impl Display for EditBuffer<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.visit(|chunk| match chunk {
+            Chunk::Original(start, end) => f.write_str(&self.input[start..end]),
+            Chunk::Edit(edit) => f.write_str(&edit.content),
+            Chunk::Generated(content) => f.write_str(content),
+        })
+    }
+}
+
+fn edit_buffer<'s>(
+    input: &'s str,
+    replacements: &[Replacement<'s>],
+) -> Result<EditBuffer<'s>, EditError> {
+    let mut buffer = EditBuffer::new(input);
+    for replacement in replacements {
+        buffer.edit(replacement.clone())?;
+    }
+    Ok(buffer)
+}
+
+pub fn apply_replacements(input: &str, replacements: &[Replacement]) -> Result<String, EditError> {
+    Ok(edit_buffer(input, replacements)?.to_string())
+}
+
+pub fn apply_replacements_with_source_map(
+    input: &str,
+    source_name: &str,
+    replacements: &[Replacement],
+) -> Result<(String, SourceMap), EditError> {
+    Ok(edit_buffer(input, replacements)?.to_string_with_source_map(source_name))
+}
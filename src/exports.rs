@@ -0,0 +1,368 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use smallvec::SmallVec;
+
+use crate::collect_dependencies;
+use crate::json;
+use crate::Dependency;
+use crate::ExportNames;
+use crate::ExportsConvention;
+use crate::HandleWarning;
+use crate::Mode;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum CssModuleReference<'s> {
+    Local { name: String },
+    Global { name: &'s str },
+    Dependency { name: &'s str, specifier: &'s str },
+}
+
+impl CssModuleReference<'_> {
+    fn write_json(&self, out: &mut String) {
+        let mut first = true;
+        out.push('{');
+        json::write_key(out, &mut first, "type");
+        match self {
+            Self::Local { name } => {
+                json::write_string(out, "local");
+                json::write_key(out, &mut first, "name");
+                json::write_string(out, name);
+            }
+            Self::Global { name } => {
+                json::write_string(out, "global");
+                json::write_key(out, &mut first, "name");
+                json::write_string(out, name);
+            }
+            Self::Dependency { name, specifier } => {
+                json::write_string(out, "dependency");
+                json::write_key(out, &mut first, "name");
+                json::write_string(out, name);
+                json::write_key(out, &mut first, "specifier");
+                json::write_string(out, specifier);
+            }
+        }
+        out.push('}');
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CssModuleExport<'s> {
+    pub local: &'s str,
+    pub export_names: SmallVec<[Cow<'s, str>; 2]>,
+    pub name: String,
+    pub composes: Vec<CssModuleReference<'s>>,
+    pub is_referenced: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CssModuleExports<'s> {
+    exports: Vec<CssModuleExport<'s>>,
+    references: Vec<(String, CssModuleReference<'s>)>,
+}
+
+impl<'s> CssModuleExports<'s> {
+    pub fn new(
+        dependencies: &[Dependency<'s>],
+        convention: ExportsConvention,
+        mut generate_local_name: impl FnMut(&'s str) -> String,
+        handle_warning: impl HandleWarning<'s>,
+    ) -> Self {
+        let mut result = Self::default();
+        let mut locals: HashMap<&'s str, usize> = HashMap::new();
+        let mut export_names = ExportNames::new(convention, handle_warning);
+        for dependency in dependencies {
+            let (local, names, name) = match dependency {
+                Dependency::ICSSExportValue { prop, value, range } => {
+                    let names = export_names.add_value(prop, range.clone());
+                    if locals.contains_key(prop) {
+                        continue;
+                    }
+                    (*prop, names, value.to_string())
+                }
+                _ => {
+                    let Some((local, range)) = dependency.local_export() else {
+                        continue;
+                    };
+                    let names = export_names.add(local, range.clone());
+                    if locals.contains_key(local) {
+                        continue;
+                    }
+                    (local, names, generate_local_name(local))
+                }
+            };
+            locals.insert(local, result.exports.len());
+            result.exports.push(CssModuleExport {
+                local,
+                export_names: names,
+                name,
+                composes: Vec::new(),
+                is_referenced: false,
+            });
+        }
+
+        for dependency in dependencies {
+            match dependency {
+                Dependency::Composes {
+                    local_classes,
+                    names,
+                    from,
+                    ..
+                } => {
+                    for name in names {
+                        let reference = match from.map(unquote) {
+                            None => {
+                                let Some(&index) = locals.get(name) else {
+                                    continue;
+                                };
+                                let export = &mut result.exports[index];
+                                export.is_referenced = true;
+                                CssModuleReference::Local {
+                                    name: export.name.clone(),
+                                }
+                            }
+                            Some("global") => CssModuleReference::Global { name },
+                            Some(specifier) => CssModuleReference::Dependency { name, specifier },
+                        };
+                        for local_class in local_classes {
+                            let Some(&index) = locals.get(local_class) else {
+                                continue;
+                            };
+                            let composes = &mut result.exports[index].composes;
+                            if !composes.contains(&reference) {
+                                composes.push(reference.clone());
+                            }
+                        }
+                    }
+                }
+                Dependency::LocalKeyframes { name, .. }
+                | Dependency::LocalCounterStyle { name, .. } => {
+                    if let Some(&index) = locals.get(name) {
+                        result.exports[index].is_referenced = true;
+                    }
+                }
+                Dependency::LocalVar {
+                    name,
+                    from: Some(from),
+                    ..
+                } => {
+                    let key = format!("--{name}");
+                    if result.references.iter().any(|(k, _)| *k == key) {
+                        continue;
+                    }
+                    let reference = match unquote(from) {
+                        "global" => CssModuleReference::Global { name },
+                        specifier => CssModuleReference::Dependency { name, specifier },
+                    };
+                    result.references.push((key, reference));
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CssModuleExport<'s>> {
+        self.exports.iter()
+    }
+
+    pub fn get(&self, export_name: &str) -> Option<&CssModuleExport<'s>> {
+        self.exports
+            .iter()
+            .find(|export| export.export_names.iter().any(|n| n == export_name))
+    }
+
+    pub fn references(&self) -> impl Iterator<Item = (&str, &CssModuleReference<'s>)> {
+        self.references.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    // Composed names from other files are written as `resolve_import(specifier, name)`, and left
+    // out when it returns None
+    pub fn to_postcss_modules_json(
+        &self,
+        mut resolve_import: impl FnMut(&str, &str) -> Option<String>,
+    ) -> String {
+        let mut out = String::from("{");
+        let mut first = true;
+        for export in &self.exports {
+            let mut value = export.name.clone();
+            for reference in &export.composes {
+                let name = match reference {
+                    CssModuleReference::Local { name } => Cow::Borrowed(name.as_str()),
+                    CssModuleReference::Global { name } => Cow::Borrowed(*name),
+                    CssModuleReference::Dependency { name, specifier } => {
+                        match resolve_import(specifier, name) {
+                            Some(name) => Cow::Owned(name),
+                            None => continue,
+                        }
+                    }
+                };
+                value.push(' ');
+                value += &name;
+            }
+            for export_name in &export.export_names {
+                json::write_key(&mut out, &mut first, export_name);
+                json::write_string(&mut out, &value);
+            }
+        }
+        out.push('}');
+        out
+    }
+
+    pub fn to_lightningcss_json(&self) -> String {
+        let mut out = String::from("{");
+        let mut first = true;
+        for export in &self.exports {
+            for export_name in &export.export_names {
+                json::write_key(&mut out, &mut first, export_name);
+                let mut first = true;
+                out.push('{');
+                json::write_key(&mut out, &mut first, "name");
+                json::write_string(&mut out, &export.name);
+                json::write_key(&mut out, &mut first, "composes");
+                out.push('[');
+                for (i, reference) in export.composes.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    reference.write_json(&mut out);
+                }
+                out.push(']');
+                json::write_key(&mut out, &mut first, "isReferenced");
+                out.push_str(if export.is_referenced {
+                    "true"
+                } else {
+                    "false"
+                });
+                out.push('}');
+            }
+        }
+        out.push('}');
+        out
+    }
+
+    pub fn to_lightningcss_references_json(&self) -> String {
+        let mut out = String::from("{");
+        let mut first = true;
+        for (key, reference) in &self.references {
+            json::write_key(&mut out, &mut first, key);
+            reference.write_json(&mut out);
+        }
+        out.push('}');
+        out
+    }
+}
+
+#[derive(Debug, Default, Clone, Hash, PartialEq, Eq)]
+pub struct ExportsDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub renamed: Vec<(String, String)>,
+    pub changed: Vec<String>,
+    pub changed_references: Vec<String>,
+}
+
+impl ExportsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.changed.is_empty()
+            && self.changed_references.is_empty()
+    }
+}
+
+// Compares what JS can observe: export names, the local name or `:export` value behind each of
+// them and their composes, declarations of the stylesheet are not part of the diff. A removed
+// name and an added one with the same composes are a rename when they have the same value, like
+// an `:export` key, or the same place among the exports, like a class whose value is its own
+// name. `changed_references` has the custom properties whose `from` was added, removed or changed
+pub fn diff_exports(
+    old: &str,
+    new: &str,
+    mode: Mode,
+    convention: ExportsConvention,
+) -> ExportsDiff {
+    let surface = |input| {
+        let (dependencies, _) = collect_dependencies(input, mode);
+        let exports =
+            CssModuleExports::new(&dependencies, convention, |local| local.to_string(), |_| {});
+        // The place of a name is the index of its export and its index among the export's names
+        let mut surface = Vec::new();
+        for (i, export) in exports.iter().enumerate() {
+            for (j, export_name) in export.export_names.iter().enumerate() {
+                surface.push((
+                    export_name.to_string(),
+                    export.name.clone(),
+                    export.composes.clone(),
+                    (i, j),
+                ));
+            }
+        }
+        let references: Vec<(String, CssModuleReference)> = exports
+            .references()
+            .map(|(key, reference)| (key.to_string(), reference.clone()))
+            .collect();
+        (surface, references)
+    };
+    let (old, old_references) = surface(old);
+    let (new, new_references) = surface(new);
+    let mut diff = ExportsDiff::default();
+    let mut added = Vec::new();
+    for (name, value, composes, place) in &new {
+        match old.iter().find(|(n, _, _, _)| n == name) {
+            None => added.push((name, value, composes, place)),
+            Some((_, old_value, old_composes, _)) => {
+                if old_value != value || old_composes != composes {
+                    diff.changed.push(name.clone());
+                }
+            }
+        }
+    }
+    for (name, value, composes, place) in &old {
+        if new.iter().any(|(n, _, _, _)| n == name) {
+            continue;
+        }
+        let index = added
+            .iter()
+            .position(|(_, v, c, _)| *v == value && *c == composes)
+            .or_else(|| {
+                added
+                    .iter()
+                    .position(|(_, _, c, p)| *c == composes && *p == place)
+            });
+        match index {
+            Some(index) => {
+                let (new_name, _, _, _) = added.remove(index);
+                diff.renamed.push((name.clone(), new_name.clone()));
+            }
+            None => diff.removed.push(name.clone()),
+        }
+    }
+    diff.added = added
+        .into_iter()
+        .map(|(name, _, _, _)| name.clone())
+        .collect();
+    for (key, reference) in &new_references {
+        match old_references.iter().find(|(k, _)| k == key) {
+            Some((_, old_reference)) if old_reference == reference => {}
+            _ => diff.changed_references.push(key.clone()),
+        }
+    }
+    for (key, _) in &old_references {
+        if !new_references.iter().any(|(k, _)| k == key) {
+            diff.changed_references.push(key.clone());
+        }
+    }
+    diff
+}
+
+pub fn unquote(s: &str) -> &str {
+    if s.len() >= 2
+        && ((s.starts_with('"') && s.ends_with('"')) || (s.starts_with('\'') && s.ends_with('\'')))
+    {
+        &s[1..s.len() - 1]
+    } else {
+        s
+    }
+}
@@ -0,0 +1,229 @@
+use crate::Lexer;
+use crate::Pos;
+use crate::Visitor;
+
+struct Observer<'a, 's> {
+    visitor: &'a mut dyn Visitor<'s>,
+    done: bool,
+}
+
+/// Fans out every callback to several visitors in one lexing pass. The primary visitor owns the
+/// lexer: it alone answers `is_selector` and may consume input (like `LexDependencies` does
+/// for `composes`), tokens it skips are not seen by the observers either. Observers are called
+/// first, each with a copy of the lexer so their side effects on it are discarded. An observer
+/// returning `None` stops receiving callbacks, the primary returning `None` stops lexing
+pub struct Fanout<'a, 's> {
+    primary: &'a mut dyn Visitor<'s>,
+    observers: Vec<Observer<'a, 's>>,
+}
+
+impl<'a, 's> Fanout<'a, 's> {
+    pub fn new(primary: &'a mut impl Visitor<'s>) -> Self {
+        Self {
+            primary,
+            observers: Vec::new(),
+        }
+    }
+
+    pub fn observe(mut self, visitor: &'a mut impl Visitor<'s>) -> Self {
+        self.observers.push(Observer {
+            visitor,
+            done: false,
+        });
+        self
+    }
+
+    fn each(
+        &mut self,
+        lexer: &Lexer<'s>,
+        mut f: impl FnMut(&mut dyn Visitor<'s>, &mut Lexer<'s>) -> Option<()>,
+    ) {
+        for observer in &mut self.observers {
+            if !observer.done {
+                observer.done = f(observer.visitor, &mut lexer.clone()).is_none();
+            }
+        }
+    }
+}
+
+impl<'s> Visitor<'s> for Fanout<'_, 's> {
+    fn function(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.function(l, start, end));
+        self.primary.function(lexer, start, end)
+    }
+
+    fn ident(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.ident(l, start, end));
+        self.primary.ident(lexer, start, end)
+    }
+
+    fn url(
+        &mut self,
+        lexer: &mut Lexer<'s>,
+        start: Pos,
+        end: Pos,
+        content_start: Pos,
+        content_end: Pos,
+    ) -> Option<()> {
+        self.each(lexer, |v, l| {
+            v.url(l, start, end, content_start, content_end)
+        });
+        self.primary
+            .url(lexer, start, end, content_start, content_end)
+    }
+
+    fn string(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.string(l, start, end));
+        self.primary.string(lexer, start, end)
+    }
+
+    fn is_selector(&mut self, lexer: &mut Lexer<'s>) -> Option<bool> {
+        self.primary.is_selector(lexer)
+    }
+
+    fn id(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.id(l, start, end));
+        self.primary.id(lexer, start, end)
+    }
+
+    fn hash(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.hash(l, start, end));
+        self.primary.hash(lexer, start, end)
+    }
+
+    fn left_parenthesis(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.left_parenthesis(l, start, end));
+        self.primary.left_parenthesis(lexer, start, end)
+    }
+
+    fn right_parenthesis(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.right_parenthesis(l, start, end));
+        self.primary.right_parenthesis(lexer, start, end)
+    }
+
+    fn comma(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.comma(l, start, end));
+        self.primary.comma(lexer, start, end)
+    }
+
+    fn class(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.class(l, start, end));
+        self.primary.class(lexer, start, end)
+    }
+
+    fn pseudo_function(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.pseudo_function(l, start, end));
+        self.primary.pseudo_function(lexer, start, end)
+    }
+
+    fn pseudo_class(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.pseudo_class(l, start, end));
+        self.primary.pseudo_class(lexer, start, end)
+    }
+
+    fn semicolon(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.semicolon(l, start, end));
+        self.primary.semicolon(lexer, start, end)
+    }
+
+    fn at_keyword(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.at_keyword(l, start, end));
+        self.primary.at_keyword(lexer, start, end)
+    }
+
+    fn left_curly_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.left_curly_bracket(l, start, end));
+        self.primary.left_curly_bracket(lexer, start, end)
+    }
+
+    fn right_curly_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.right_curly_bracket(l, start, end));
+        self.primary.right_curly_bracket(lexer, start, end)
+    }
+
+    fn left_square_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.left_square_bracket(l, start, end));
+        self.primary.left_square_bracket(lexer, start, end)
+    }
+
+    fn right_square_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.right_square_bracket(l, start, end));
+        self.primary.right_square_bracket(lexer, start, end)
+    }
+
+    fn number(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.number(l, start, end));
+        self.primary.number(lexer, start, end)
+    }
+
+    fn percentage(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.percentage(l, start, end));
+        self.primary.percentage(lexer, start, end)
+    }
+
+    fn dimension(
+        &mut self,
+        lexer: &mut Lexer<'s>,
+        start: Pos,
+        end: Pos,
+        unit_start: Pos,
+    ) -> Option<()> {
+        self.each(lexer, |v, l| v.dimension(l, start, end, unit_start));
+        self.primary.dimension(lexer, start, end, unit_start)
+    }
+
+    fn unicode_range(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.unicode_range(l, start, end));
+        self.primary.unicode_range(lexer, start, end)
+    }
+
+    fn colon(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.colon(l, start, end));
+        self.primary.colon(lexer, start, end)
+    }
+
+    fn cdo(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.cdo(l, start, end));
+        self.primary.cdo(lexer, start, end)
+    }
+
+    fn cdc(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.cdc(l, start, end));
+        self.primary.cdc(lexer, start, end)
+    }
+
+    fn delim(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.delim(l, start, end));
+        self.primary.delim(lexer, start, end)
+    }
+
+    fn whitespace(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.whitespace(l, start, end));
+        self.primary.whitespace(lexer, start, end)
+    }
+
+    fn comment(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.comment(l, start, end));
+        self.primary.comment(lexer, start, end)
+    }
+
+    fn bad_url(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.bad_url(l, start, end));
+        self.primary.bad_url(lexer, start, end)
+    }
+
+    fn bad_string(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.bad_string(l, start, end));
+        self.primary.bad_string(lexer, start, end)
+    }
+
+    fn unterminated_comment(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.unterminated_comment(l, start, end));
+        self.primary.unterminated_comment(lexer, start, end)
+    }
+
+    fn eof(&mut self, lexer: &mut Lexer<'s>, pos: Pos) -> Option<()> {
+        self.each(lexer, |v, l| v.eof(l, pos));
+        self.primary.eof(lexer, pos)
+    }
+}
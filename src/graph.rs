@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+
+use crate::collect_dependencies;
+use crate::exports::unquote;
+use crate::json;
+use crate::owned::OwnedDependency;
+use crate::owned::OwnedWarning;
+use crate::path;
+use crate::rebase::is_rebasable;
+use crate::Dependency;
+use crate::Mode;
+use crate::Range;
+use crate::Warning;
+use crate::WarningKind;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    pub id: String,
+    pub source: String,
+}
+
+pub trait Resolver {
+    fn resolve(&mut self, request: &str, importer: &str) -> Option<Resolved>;
+}
+
+impl<F: FnMut(&str, &str) -> Option<Resolved>> Resolver for F {
+    fn resolve(&mut self, request: &str, importer: &str) -> Option<Resolved> {
+        self(request, importer)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MemoryResolver {
+    files: HashMap<String, String>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, id: impl Into<String>, source: impl Into<String>) -> &mut Self {
+        self.files.insert(id.into(), source.into());
+        self
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.files.get(id).map(|s| s.as_str())
+    }
+
+    // Requests without `./` are relative to the importer too, like `@import "base.css"` is in CSS
+    pub fn resolve_id(&self, request: &str, importer: &str) -> String {
+        if request.starts_with('/') || is_rebasable(request) {
+            path::join(importer, request)
+        } else {
+            request.to_string()
+        }
+    }
+}
+
+impl Resolver for MemoryResolver {
+    fn resolve(&mut self, request: &str, importer: &str) -> Option<Resolved> {
+        let id = self.resolve_id(request, importer);
+        let source = self.files.get(&id)?.clone();
+        Some(Resolved { id, source })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ModuleId(u32);
+
+impl ModuleId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum EdgeKind {
+    Import,
+    Composes,
+    Var,
+    ICSS,
+    Url,
+}
+
+impl EdgeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeKind::Import => "import",
+            EdgeKind::Composes => "composes",
+            EdgeKind::Var => "var",
+            EdgeKind::ICSS => "icss",
+            EdgeKind::Url => "url",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Edge {
+    pub kind: EdgeKind,
+    pub request: String,
+    pub range: Option<Range>,
+    pub dependency: usize,
+    pub target: Option<ModuleId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    id: String,
+    source: String,
+    dependencies: Vec<OwnedDependency>,
+    warnings: Vec<OwnedWarning>,
+    edges: Vec<Edge>,
+}
+
+impl Module {
+    fn new(id: String, source: String, mode: Mode) -> Self {
+        let (dependencies, warnings) = collect_dependencies(&source, mode);
+        let mut edges = Vec::new();
+        for (index, dependency) in dependencies.iter().enumerate() {
+            if let Some((kind, request)) = edge(dependency) {
+                edges.push(Edge {
+                    kind,
+                    request: request.to_string(),
+                    range: dependency.range().cloned(),
+                    dependency: index,
+                    target: None,
+                });
+            }
+        }
+        let dependencies = dependencies.into_iter().map(|d| d.into_owned()).collect();
+        let warnings = warnings.into_iter().map(|w| w.into_owned()).collect();
+        Self {
+            id,
+            source,
+            dependencies,
+            warnings,
+            edges,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn dependencies(&self) -> &[OwnedDependency] {
+        &self.dependencies
+    }
+
+    // Warnings of lexing the source
+    pub fn warnings(&self) -> &[OwnedWarning] {
+        &self.warnings
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+}
+
+fn edge<'a>(dependency: &Dependency<'a>) -> Option<(EdgeKind, &'a str)> {
+    let external = |from: &'a str| Some(unquote(from)).filter(|from| *from != "global");
+    match *dependency {
+        Dependency::Import { request, .. } => Some((EdgeKind::Import, request)),
+        Dependency::Url { request, .. } => Some((EdgeKind::Url, request)),
+        Dependency::Composes {
+            from: Some(from), ..
+        } => external(from).map(|from| (EdgeKind::Composes, from)),
+        Dependency::LocalVar {
+            from: Some(from), ..
+        } => external(from).map(|from| (EdgeKind::Var, from)),
+        Dependency::ICSSImportFrom { path } => Some((EdgeKind::ICSS, unquote(path))),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleGraph {
+    mode: Mode,
+    modules: Vec<Module>,
+    ids: HashMap<String, ModuleId>,
+}
+
+impl ModuleGraph {
+    pub fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            modules: Vec::new(),
+            ids: HashMap::new(),
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn insert(&mut self, id: String, source: String) -> (ModuleId, bool) {
+        if let Some(&module_id) = self.ids.get(&id) {
+            return (module_id, false);
+        }
+        let module_id = ModuleId(self.modules.len() as u32);
+        self.ids.insert(id.clone(), module_id);
+        self.modules.push(Module::new(id, source, self.mode));
+        (module_id, true)
+    }
+
+    // Url edges point at assets, which are recorded but never resolved into modules
+    pub fn add_entry(
+        &mut self,
+        id: impl Into<String>,
+        source: impl Into<String>,
+        resolver: &mut impl Resolver,
+    ) -> ModuleId {
+        let (entry, _) = self.insert(id.into(), source.into());
+        let mut queue = vec![entry];
+        while let Some(module_id) = queue.pop() {
+            let mut resolved: HashMap<String, Option<ModuleId>> = HashMap::new();
+            for index in 0..self.modules[module_id.index()].edges.len() {
+                let module = &self.modules[module_id.index()];
+                let edge = &module.edges[index];
+                if edge.kind == EdgeKind::Url || edge.target.is_some() {
+                    continue;
+                }
+                let target = match resolved.get(&edge.request) {
+                    Some(&target) => target,
+                    None => {
+                        let request = edge.request.clone();
+                        let importer = module.id.clone();
+                        let target = resolver.resolve(&request, &importer).map(|r| {
+                            let (target, added) = self.insert(r.id, r.source);
+                            if added {
+                                queue.push(target);
+                            }
+                            target
+                        });
+                        resolved.insert(request, target);
+                        target
+                    }
+                };
+                self.modules[module_id.index()].edges[index].target = target;
+            }
+        }
+        entry
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    pub fn get(&self, id: &str) -> Option<ModuleId> {
+        self.ids.get(id).copied()
+    }
+
+    pub fn module(&self, id: ModuleId) -> &Module {
+        &self.modules[id.index()]
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = (ModuleId, &Module)> {
+        self.modules
+            .iter()
+            .enumerate()
+            .map(|(i, module)| (ModuleId(i as u32), module))
+    }
+
+    pub fn dependents(&self, id: ModuleId) -> impl Iterator<Item = (ModuleId, &Edge)> {
+        self.modules().flat_map(move |(module_id, module)| {
+            module
+                .edges
+                .iter()
+                .filter(move |edge| edge.target == Some(id))
+                .map(move |edge| (module_id, edge))
+        })
+    }
+
+    // Dependencies come before their dependents, edges that close a cycle are skipped
+    pub fn post_order(&self, entry: ModuleId) -> Vec<ModuleId> {
+        let mut order = Vec::new();
+        let mut visited = vec![false; self.modules.len()];
+        let mut stack = vec![(entry, 0)];
+        visited[entry.index()] = true;
+        while let Some((module_id, index)) = stack.pop() {
+            let edges = &self.modules[module_id.index()].edges;
+            match edges[index..]
+                .iter()
+                .position(|edge| edge.target.is_some_and(|target| !visited[target.index()]))
+            {
+                Some(offset) => {
+                    let target = edges[index + offset].target.unwrap();
+                    visited[target.index()] = true;
+                    stack.push((module_id, index + offset + 1));
+                    stack.push((target, 0));
+                }
+                None => order.push(module_id),
+            }
+        }
+        order
+    }
+
+    // Strongly connected components with more than one module, or with a module that depends
+    // on itself, sorted by their first module
+    pub fn cycles(&self) -> Vec<Vec<ModuleId>> {
+        let len = self.modules.len();
+        let mut index = vec![usize::MAX; len];
+        let mut low_link = vec![0; len];
+        let mut on_stack = vec![false; len];
+        let mut stack = Vec::new();
+        let mut next = 0;
+        let mut cycles = Vec::new();
+        for root in 0..len {
+            if index[root] != usize::MAX {
+                continue;
+            }
+            index[root] = next;
+            low_link[root] = next;
+            next += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            let mut work = vec![(root, 0)];
+            while let Some(&(v, i)) = work.last() {
+                if let Some(edge) = self.modules[v].edges.get(i) {
+                    work.last_mut().unwrap().1 += 1;
+                    let Some(w) = edge.target.map(ModuleId::index) else {
+                        continue;
+                    };
+                    if index[w] == usize::MAX {
+                        index[w] = next;
+                        low_link[w] = next;
+                        next += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        work.push((w, 0));
+                    } else if on_stack[w] {
+                        low_link[v] = low_link[v].min(index[w]);
+                    }
+                    continue;
+                }
+                work.pop();
+                if let Some(&(parent, _)) = work.last() {
+                    low_link[parent] = low_link[parent].min(low_link[v]);
+                }
+                if low_link[v] != index[v] {
+                    continue;
+                }
+                let mut component = Vec::new();
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component.push(ModuleId(w as u32));
+                    if w == v {
+                        break;
+                    }
+                }
+                let self_loop = self.modules[v]
+                    .edges
+                    .iter()
+                    .any(|edge| edge.target == Some(ModuleId(v as u32)));
+                if component.len() > 1 || self_loop {
+                    component.sort();
+                    cycles.push(component);
+                }
+            }
+        }
+        cycles.sort();
+        cycles
+    }
+
+    // One warning per cycle, at the first edge of the cycle that has a range
+    pub fn cycle_warnings<'g>(&'g self, mut handle_warning: impl FnMut(ModuleId, Warning<'g>)) {
+        for cycle in self.cycles() {
+            let edge = cycle.iter().find_map(|&module_id| {
+                let edge = self.modules[module_id.index()].edges.iter().find(|edge| {
+                    edge.range.is_some() && edge.target.is_some_and(|t| cycle.contains(&t))
+                })?;
+                Some((module_id, edge))
+            });
+            let Some((module_id, edge)) = edge else {
+                continue;
+            };
+            handle_warning(
+                module_id,
+                Warning::new(
+                    edge.range.clone().unwrap(),
+                    WarningKind::DependencyCycle {
+                        name: &edge.request,
+                        modules: cycle.len(),
+                    },
+                ),
+            );
+        }
+    }
+
+    fn resolved_edges(&self, module_id: ModuleId) -> Vec<(ModuleId, EdgeKind)> {
+        let mut edges = Vec::new();
+        for edge in &self.modules[module_id.index()].edges {
+            if let Some(target) = edge.target {
+                if !edges.contains(&(target, edge.kind)) {
+                    edges.push((target, edge.kind));
+                }
+            }
+        }
+        edges
+    }
+
+    // Edges between modules of the same cycle are colored red
+    pub fn to_dot(&self) -> String {
+        let cycles = self.cycles();
+        let mut out = String::from("digraph {\n");
+        for module in &self.modules {
+            out += "  ";
+            json::write_string(&mut out, &module.id);
+            out += ";\n";
+        }
+        for (module_id, module) in self.modules() {
+            for (target, kind) in self.resolved_edges(module_id) {
+                out += "  ";
+                json::write_string(&mut out, &module.id);
+                out += " -> ";
+                json::write_string(&mut out, &self.module(target).id);
+                out += &format!(" [label=\"{}\"", kind.as_str());
+                if cycles
+                    .iter()
+                    .any(|cycle| cycle.contains(&module_id) && cycle.contains(&target))
+                {
+                    out += ", color=red";
+                }
+                out += "];\n";
+            }
+        }
+        out += "}\n";
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"modules\":{");
+        let mut first = true;
+        for (module_id, module) in self.modules() {
+            json::write_key(&mut out, &mut first, &module.id);
+            out.push('[');
+            for (i, (target, kind)) in self.resolved_edges(module_id).into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                let mut first = true;
+                out.push('{');
+                json::write_key(&mut out, &mut first, "target");
+                json::write_string(&mut out, &self.module(target).id);
+                json::write_key(&mut out, &mut first, "kind");
+                json::write_string(&mut out, kind.as_str());
+                out.push('}');
+            }
+            out.push(']');
+        }
+        out += "},\"cycles\":[";
+        for (i, cycle) in self.cycles().iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push('[');
+            for (j, &module_id) in cycle.iter().enumerate() {
+                if j > 0 {
+                    out.push(',');
+                }
+                json::write_string(&mut out, &self.module(module_id).id);
+            }
+            out.push(']');
+        }
+        out += "]}";
+        out
+    }
+}
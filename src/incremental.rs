@@ -0,0 +1,188 @@
+use crate::dependencies::SplitPoint;
+use crate::dependencies::SplitState;
+use crate::stream::LOOKAHEAD;
+use crate::Dependency;
+use crate::LexDependencies;
+use crate::Lexer;
+use crate::Mode;
+use crate::OwnedDependency;
+use crate::OwnedWarning;
+use crate::Pos;
+use crate::Range;
+use crate::Replacement;
+use crate::Warning;
+
+// The dependencies and warnings of an input that is edited over time. An edit is lexed from the
+// end of the last rule before it, up to the end of a rule after it where lexing reaches the same
+// state as before, the results after that are moved instead of lexed again
+#[derive(Debug, Clone)]
+pub struct IncrementalLex {
+    mode: Mode,
+    dependencies: Vec<OwnedDependency>,
+    warnings: Vec<OwnedWarning>,
+    splits: Vec<SplitPoint>,
+    // Length of the input the results are for
+    len: Pos,
+}
+
+impl IncrementalLex {
+    pub fn new(input: &str, mode: Mode) -> Self {
+        let mut result = Self {
+            mode,
+            dependencies: Vec::new(),
+            warnings: Vec::new(),
+            splits: Vec::new(),
+            len: input.len() as Pos,
+        };
+        result.lex(input, 0, None, Vec::new());
+        result
+    }
+
+    pub fn dependencies(&self) -> &[OwnedDependency] {
+        &self.dependencies
+    }
+
+    pub fn warnings(&self) -> &[OwnedWarning] {
+        &self.warnings
+    }
+
+    // Updates the results for `edit` of the previous input, `input` is the input after the edit.
+    // Returns the range of `input` that was lexed again. An edit that doesn't fit the previous
+    // input or doesn't lead to `input` lexes the whole input again
+    pub fn edit(&mut self, input: &str, edit: &Replacement) -> Range {
+        let Range { start, end } = edit.range;
+        let content_end = start as usize + edit.content.len();
+        if start > end
+            || end > self.len
+            || input.len() != (self.len - (end - start)) as usize + edit.content.len()
+            || input.get(start as usize..content_end) != Some(&*edit.content)
+        {
+            *self = Self::new(input, self.mode);
+            return Range::new(0, self.len);
+        }
+        self.len = input.len() as Pos;
+        let delta = edit.content.len() as i64 - (end - start) as i64;
+        let shift = |pos: Pos| {
+            if pos >= end {
+                (pos as i64 + delta) as Pos
+            } else {
+                pos
+            }
+        };
+        let shift_state = |state: &SplitState| SplitState {
+            pure_global: state.pure_global.map(shift),
+            open_blocks: state.open_blocks.iter().copied().map(shift).collect(),
+            ..state.clone()
+        };
+
+        // The rules before the split point are lexed the same, as long as the edit is after the
+        // input the lexer looks ahead at
+        let restart = self
+            .splits
+            .partition_point(|split| split.end + LOOKAHEAD as Pos <= start);
+        let resume = restart.checked_sub(1).map(|i| self.splits[i].clone());
+        let stops = self.splits[restart..]
+            .iter()
+            .filter(|split| {
+                // A block is still open after the edit when its `{` is before or after it
+                split.end >= end
+                    && split
+                        .state
+                        .pure_global
+                        .map_or(true, |pos| pos <= start || pos >= end)
+                    && split
+                        .state
+                        .open_blocks
+                        .iter()
+                        .all(|&pos| pos < start || pos >= end)
+            })
+            .map(|split| (shift(split.end), shift_state(&split.state)))
+            .collect();
+
+        let (from, dependencies, warnings, state) = match &resume {
+            Some(split) => (
+                split.end,
+                split.dependencies,
+                split.warnings,
+                Some(split.state.clone()),
+            ),
+            None => (0, 0, 0, None),
+        };
+        let old_splits = self.splits.split_off(restart);
+        let old_dependencies = self.dependencies.split_off(dependencies);
+        let old_warnings = self.warnings.split_off(warnings);
+
+        if !self.lex(input, from, state, stops) {
+            return Range::new(from, input.len() as Pos);
+        }
+
+        // Lexing stopped at a split point of the previous input, the rest is the same but moved
+        let stop = self.splits.last().unwrap().clone();
+        let i = old_splits
+            .iter()
+            .position(|split| split.end >= end && shift(split.end) == stop.end)
+            .unwrap();
+        let old_stop = &old_splits[i];
+        self.dependencies.extend(
+            old_dependencies[old_stop.dependencies - dependencies..]
+                .iter()
+                .cloned()
+                .map(|mut dependency| {
+                    if let Some(range) = dependency.range_mut() {
+                        *range = Range::new(shift(range.start), shift(range.end));
+                    }
+                    dependency
+                }),
+        );
+        self.warnings.extend(
+            old_warnings[old_stop.warnings - warnings..]
+                .iter()
+                .cloned()
+                .map(|mut warning| {
+                    warning.range =
+                        Range::new(shift(warning.range.start), shift(warning.range.end));
+                    warning
+                }),
+        );
+        self.splits
+            .extend(old_splits[i + 1..].iter().map(|split| SplitPoint {
+                end: shift(split.end),
+                state: shift_state(&split.state),
+                dependencies: split.dependencies - old_stop.dependencies + stop.dependencies,
+                warnings: split.warnings - old_stop.warnings + stop.warnings,
+            }));
+        Range::new(from, stop.end)
+    }
+
+    // Lexes `input` from `from` and returns whether it stopped at one of `stops`
+    fn lex(
+        &mut self,
+        input: &str,
+        from: Pos,
+        state: Option<SplitState>,
+        stops: Vec<(Pos, SplitState)>,
+    ) -> bool {
+        let dependencies = self.dependencies.len();
+        let warnings = self.warnings.len();
+        let handle_dependency = &mut self.dependencies;
+        let handle_warning = &mut self.warnings;
+        let mut visitor = LexDependencies::new(
+            |dependency: Dependency| handle_dependency.push(dependency.into_owned()),
+            |warning: Warning| handle_warning.push(warning.into_owned()),
+            self.mode,
+        );
+        if let Some(state) = state {
+            visitor.set_split_state(state);
+        }
+        visitor.record_splits(Pos::MAX);
+        visitor.stop_at_splits(stops);
+        Lexer::new(input).lex_from(from, &mut visitor);
+        let (splits, stopped) = visitor.into_splits();
+        self.splits.extend(splits.into_iter().map(|mut split| {
+            split.dependencies += dependencies;
+            split.warnings += warnings;
+            split
+        }));
+        stopped
+    }
+}
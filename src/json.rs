@@ -0,0 +1,289 @@
+use std::fmt::Write;
+
+pub fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+pub fn write_key(out: &mut String, first: &mut bool, key: &str) {
+    if !std::mem::take(first) {
+        out.push(',');
+    }
+    write_string(out, key);
+    out.push(':');
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let Value::Object(entries) = self else {
+            return None;
+        };
+        entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        let Value::String(s) = self else {
+            return None;
+        };
+        Some(s)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        let Value::Number(n) = self else {
+            return None;
+        };
+        Some(*n)
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        let Value::Array(items) = self else {
+            return None;
+        };
+        Some(items)
+    }
+}
+
+// Arrays and objects nested deeper than this are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 128;
+
+pub fn parse(input: &str) -> Option<Value> {
+    let mut parser = Parser {
+        input: input.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.white_space();
+    if parser.pos != parser.input.len() {
+        return None;
+    }
+    Some(value)
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn white_space(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, literal: &[u8]) -> Option<()> {
+        if !self.input[self.pos..].starts_with(literal) {
+            return None;
+        }
+        self.pos += literal.len();
+        Some(())
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.white_space();
+        match self.peek()? {
+            b'n' => self.eat(b"null").map(|_| Value::Null),
+            b't' => self.eat(b"true").map(|_| Value::Bool(true)),
+            b'f' => self.eat(b"false").map(|_| Value::Bool(false)),
+            b'"' => self.string().map(Value::String),
+            c @ (b'[' | b'{') => {
+                if self.depth == MAX_DEPTH {
+                    return None;
+                }
+                self.depth += 1;
+                let value = if c == b'[' {
+                    self.array()
+                } else {
+                    self.object()
+                };
+                self.depth -= 1;
+                value
+            }
+            _ => self.number().map(Value::Number),
+        }
+    }
+
+    fn array(&mut self) -> Option<Value> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.white_space();
+        if self.peek()? == b']' {
+            self.pos += 1;
+            return Some(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.white_space();
+            match self.peek()? {
+                b',' => self.pos += 1,
+                b']' => {
+                    self.pos += 1;
+                    return Some(Value::Array(items));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn object(&mut self) -> Option<Value> {
+        self.pos += 1;
+        let mut entries = Vec::new();
+        self.white_space();
+        if self.peek()? == b'}' {
+            self.pos += 1;
+            return Some(Value::Object(entries));
+        }
+        loop {
+            self.white_space();
+            let key = self.string()?;
+            self.white_space();
+            self.eat(b":")?;
+            entries.push((key, self.value()?));
+            self.white_space();
+            match self.peek()? {
+                b',' => self.pos += 1,
+                b'}' => {
+                    self.pos += 1;
+                    return Some(Value::Object(entries));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        let start = self.pos;
+        while matches!(
+            self.peek(),
+            Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        ) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let hex = std::str::from_utf8(self.input.get(self.pos..self.pos + 4)?).ok()?;
+        self.pos += 4;
+        u32::from_str_radix(hex, 16).ok()
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.eat(b"\"")?;
+        let mut out = Vec::new();
+        loop {
+            let c = self.peek()?;
+            self.pos += 1;
+            match c {
+                b'"' => return String::from_utf8(out).ok(),
+                b'\\' => {
+                    let c = self.peek()?;
+                    self.pos += 1;
+                    let escaped = match c {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            if (0xd800..0xdc00).contains(&code) {
+                                self.eat(b"\\u")?;
+                                let low = self.hex4()?;
+                                code = 0x10000
+                                    + ((code - 0xd800) << 10)
+                                    + (low.checked_sub(0xdc00)?);
+                            }
+                            char::from_u32(code)?
+                        }
+                        _ => return None,
+                    };
+                    let mut buf = [0; 4];
+                    out.extend_from_slice(escaped.encode_utf8(&mut buf).as_bytes());
+                }
+                c => out.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_string() {
+        let mut out = String::new();
+        write_string(&mut out, "a\"b\\c\nd\u{1}é😀");
+        assert_eq!(out, r#""a\"b\\c\nd\u0001é😀""#);
+        assert_eq!(
+            parse(&out),
+            Some(Value::String("a\"b\\c\nd\u{1}é😀".to_string()))
+        );
+        assert_eq!(parse(r#""😀\/""#), Some(Value::String("😀/".to_string())));
+    }
+
+    #[test]
+    fn parse_values() {
+        let value = parse(r#" {"a": [1, -2.5e1, true, false, null], "b": {}, "c": []} "#).unwrap();
+        assert_eq!(
+            value.get("a"),
+            Some(&Value::Array(vec![
+                Value::Number(1.0),
+                Value::Number(-25.0),
+                Value::Bool(true),
+                Value::Bool(false),
+                Value::Null,
+            ]))
+        );
+        assert_eq!(value.get("b"), Some(&Value::Object(vec![])));
+        assert_eq!(value.get("c").and_then(|c| c.as_array()), Some(&[][..]));
+        assert!(parse("{").is_none());
+        assert!(parse(r#"{"a" 1}"#).is_none());
+        assert!(parse("[1,]").is_none());
+        assert!(parse("1 2").is_none());
+    }
+
+    #[test]
+    fn depth_limit() {
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(parse(&nested(MAX_DEPTH)).is_some());
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_none());
+        assert!(parse(&"[{\"a\":".repeat(100_000)).is_none());
+    }
+}
@@ -1,7 +1,12 @@
 use std::{iter::Rev, str::Chars};
 
+use memchr::{memchr2, memchr3, memmem};
+
 use crate::Range;
 
+// Stands in for the end of input in lookahead checks, it never starts or continues a token
+pub(crate) const EOF: char = '\0';
+
 pub const C_LINE_FEED: char = '\n';
 pub const C_CARRIAGE_RETURN: char = '\r';
 pub const C_FORM_FEED: char = '\u{c}';
@@ -34,10 +39,12 @@ pub const C_LOW_LINE: char = '_';
 pub const C_LOWER_A: char = 'a';
 pub const C_LOWER_E: char = 'e';
 pub const C_LOWER_F: char = 'f';
+pub const C_LOWER_U: char = 'u';
 pub const C_LOWER_Z: char = 'z';
 pub const C_UPPER_A: char = 'A';
 pub const C_UPPER_E: char = 'E';
 pub const C_UPPER_F: char = 'F';
+pub const C_UPPER_U: char = 'U';
 pub const C_UPPER_Z: char = 'Z';
 pub const C_0: char = '0';
 pub const C_9: char = '9';
@@ -48,37 +55,252 @@ pub const C_HYPHEN_MINUS: char = '-';
 
 pub const C_LESS_THAN_SIGN: char = '<';
 pub const C_GREATER_THAN_SIGN: char = '>';
+pub const C_EXCLAMATION_MARK: char = '!';
+pub const C_QUESTION_MARK: char = '?';
 
 pub type Pos = u32;
 
+/// Callbacks invoked by [`Lexer::lex`] for the tokens that matter to CSS Modules.
+///
+/// Every `start`/`end` pair is a byte range into the lexed input, so `lexer.slice(start, end)`
+/// returns the token text. The lexer is passed mutably so a visitor can consume more input
+/// itself, lexing continues from wherever the visitor leaves it. Returning `None` from any
+/// callback stops lexing.
+///
+/// All methods default to doing nothing, implement only the ones you need.
 pub trait Visitor<'s> {
-    fn function(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn ident(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
+    /// An identifier followed by `(`, the range includes the `(`. `url(` is reported here
+    /// when its argument is a quoted string.
+    fn function(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    fn ident(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// An unquoted `url(...)`, `start..end` covers the whole function and
+    /// `content_start..content_end` the url without surrounding whitespace.
     fn url(
         &mut self,
-        lexer: &mut Lexer<'s>,
-        start: Pos,
-        end: Pos,
-        content_start: Pos,
-        content_end: Pos,
-    ) -> Option<()>;
-    fn string(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn is_selector(&mut self, lexer: &mut Lexer<'s>) -> Option<bool>;
-    fn id(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn left_parenthesis(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn right_parenthesis(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn comma(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn class(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn pseudo_function(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn pseudo_class(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn semicolon(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn at_keyword(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn left_curly_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
-    fn right_curly_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()>;
+        _lexer: &mut Lexer<'s>,
+        _start: Pos,
+        _end: Pos,
+        _content_start: Pos,
+        _content_end: Pos,
+    ) -> Option<()> {
+        Some(())
+    }
+
+    /// A string token including its quotes.
+    fn string(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// Queried after a `.`, `#` or `:` has been consumed and before the name that follows it,
+    /// to decide whether it is a selector. When this returns `false` the name is lexed as
+    /// ordinary tokens and no `class`, `id`, `pseudo_function` or `pseudo_class` is reported,
+    /// the `.` or `:` itself is reported as a `delim` or `colon` instead and a `#` with its name
+    /// as a `hash`. Also queried
+    /// before `u+` followed by a hex digit or `?`, which is only lexed as a `unicode_range` when
+    /// this returns `false` since `u+a` is a valid selector as well.
+    /// Defaults to `true`, so hex colors like `#fff` are reported as ids.
+    fn is_selector(&mut self, _lexer: &mut Lexer<'s>) -> Option<bool> {
+        Some(true)
+    }
+
+    /// A `#` followed by a name, including the `#`. A lone `#` is reported as well.
+    fn id(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A `#` followed by a name that `is_selector` rejected, like a hex color in a value,
+    /// including the `#`.
+    fn hash(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    fn left_parenthesis(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    fn right_parenthesis(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    fn comma(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A `.` followed by a name, including the `.`.
+    fn class(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A `:` followed by a name and `(`, including both, e.g. `:global(`. `::` pseudo
+    /// elements are reported with `start` at the second colon.
+    fn pseudo_function(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A `:` followed by a name, including the `:`.
+    fn pseudo_class(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    fn semicolon(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// An `@` followed by a name, including the `@`.
+    fn at_keyword(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    fn left_curly_bracket(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    fn right_curly_bracket(
+        &mut self,
+        _lexer: &mut Lexer<'s>,
+        _start: Pos,
+        _end: Pos,
+    ) -> Option<()> {
+        Some(())
+    }
+
+    fn left_square_bracket(
+        &mut self,
+        _lexer: &mut Lexer<'s>,
+        _start: Pos,
+        _end: Pos,
+    ) -> Option<()> {
+        Some(())
+    }
+
+    fn right_square_bracket(
+        &mut self,
+        _lexer: &mut Lexer<'s>,
+        _start: Pos,
+        _end: Pos,
+    ) -> Option<()> {
+        Some(())
+    }
+
+    /// A number without unit, including its sign.
+    fn number(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A number followed by `%`, including the `%`.
+    fn percentage(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A number followed by a unit, `unit_start..end` is the range of the unit.
+    fn dimension(
+        &mut self,
+        _lexer: &mut Lexer<'s>,
+        _start: Pos,
+        _end: Pos,
+        _unit_start: Pos,
+    ) -> Option<()> {
+        Some(())
+    }
+
+    /// A `u+` range like `U+0-7F` or `u+4??`, see `is_selector` for when it is recognized.
+    fn unicode_range(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A `:` that doesn't start a pseudo class or pseudo function.
+    fn colon(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// `<!--`
+    fn cdo(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// `-->`
+    fn cdc(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// Any other single code point, like `+`, `>`, `*` or `!`.
+    fn delim(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A run of whitespace, whitespace at the end of input is reported too.
+    fn whitespace(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A comment including `/*` and `*/`, an unterminated comment at the end of input is
+    /// reported too.
+    fn comment(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// An unquoted `url(` containing whitespace before its end or a `(`. The range extends to
+    /// the next `)`, but lexing continues right after the offending character.
+    fn bad_url(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A string that reaches an unescaped newline, reported before the `string` callback of
+    /// the same string.
+    fn bad_string(&mut self, _lexer: &mut Lexer<'s>, _start: Pos, _end: Pos) -> Option<()> {
+        Some(())
+    }
+
+    /// A comment that runs to the end of input, reported after its `comment` callback.
+    fn unterminated_comment(
+        &mut self,
+        _lexer: &mut Lexer<'s>,
+        _start: Pos,
+        _end: Pos,
+    ) -> Option<()> {
+        Some(())
+    }
+
+    /// Called once lexing has stopped, either at the end of input or because a callback
+    /// returned `None`, `pos` is where the lexer stopped.
+    fn eof(&mut self, _lexer: &mut Lexer<'s>, _pos: Pos) -> Option<()> {
+        Some(())
+    }
+}
+
+/// Yields the chars of the input like `Chars` but works on the bytes, only non-ASCII bytes go
+/// through UTF-8 decoding. The byte position lets the lexer scan ahead without decoding. This is
+/// the default iterator of `Lexer` in place of `Chars`, so `Lexer<'s>` names
+/// `Lexer<'s, Utf8Bytes<'s>>`.
+#[derive(Debug, Clone)]
+pub struct Utf8Bytes<'s> {
+    value: &'s str,
+    pos: usize,
+}
+
+impl Iterator for Utf8Bytes<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let b = *self.value.as_bytes().get(self.pos)?;
+        if b.is_ascii() {
+            self.pos += 1;
+            return Some(b as char);
+        }
+        let c = self.value[self.pos..].chars().next()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct Lexer<'s, I: Iterator<Item = char> = Chars<'s>> {
+pub struct Lexer<'s, I: Iterator<Item = char> = Utf8Bytes<'s>> {
     value: &'s str,
     iter: I,
     cur_pos: Option<Pos>,
@@ -89,7 +311,7 @@ pub struct Lexer<'s, I: Iterator<Item = char> = Chars<'s>> {
 
 impl<'s> Lexer<'s> {
     pub fn new(value: &'s str) -> Self {
-        let mut iter = value.chars();
+        let mut iter = Utf8Bytes { value, pos: 0 };
         let peek = iter.next();
         let peek2 = iter.next();
         Self {
@@ -124,6 +346,54 @@ impl<'s> Lexer<'s> {
     pub fn slice_range<'a>(input: &'a str, range: &Range) -> Option<&'a str> {
         input.get(range.start as usize..range.end as usize)
     }
+
+    // Moves to `pos` as if every char before it was consumed, `pos` must be a char boundary
+    fn seek(&mut self, pos: usize) {
+        self.iter.pos = pos;
+        self.cur_pos = Some(pos as Pos);
+        self.cur = self.iter.next();
+        self.peek = self.iter.next();
+        self.peek2 = self.iter.next();
+    }
+
+    // Moves to the first byte found by `find` from the current position, or to the end of input.
+    // The byte must start a char, e.g. an ASCII byte
+    fn skip_until(&mut self, find: impl Fn(&[u8]) -> Option<usize>) {
+        let Some(start) = self.cur_pos() else {
+            return;
+        };
+        let start = start as usize;
+        let rest = &self.value.as_bytes()[start..];
+        let len = find(rest).unwrap_or(rest.len());
+        if len > 0 {
+            self.seek(start + len);
+        }
+    }
+
+    // Skips ident code points, only non-ASCII bytes are decoded, escapes are left to the caller
+    fn skip_ident(&mut self) {
+        let Some(start) = self.cur_pos() else {
+            return;
+        };
+        let start = start as usize;
+        let bytes = self.value.as_bytes();
+        let mut pos = start;
+        while let Some(&b) = bytes.get(pos) {
+            if is_ident_byte(b) {
+                pos += 1;
+            } else if b.is_ascii() {
+                break;
+            } else {
+                match self.value[pos..].chars().next() {
+                    Some(c) if is_ident(c) => pos += c.len_utf8(),
+                    _ => break,
+                }
+            }
+        }
+        if pos > start {
+            self.seek(pos);
+        }
+    }
 }
 
 impl<'s, I: Iterator<Item = char>> Lexer<'s, I> {
@@ -162,81 +432,191 @@ impl<'s, I: Iterator<Item = char>> Lexer<'s, I> {
     pub fn peek2(&self) -> Option<char> {
         self.peek2
     }
+
+    // Lookahead that doesn't give up at the end of input, a token that ends the input is complete
+    pub(crate) fn cur_or_eof(&self) -> char {
+        self.cur.unwrap_or(EOF)
+    }
+
+    pub(crate) fn peek_or_eof(&self) -> char {
+        self.peek.unwrap_or(EOF)
+    }
+
+    pub(crate) fn peek2_or_eof(&self) -> char {
+        self.peek2.unwrap_or(EOF)
+    }
 }
 
 impl<'s> Lexer<'s> {
     pub fn lex<T: Visitor<'s>>(&mut self, visitor: &mut T) {
+        self.consume();
+        self.lex_rest(visitor);
+    }
+
+    // Lexes from `pos`, which must be where a token starts
+    pub(crate) fn lex_from<T: Visitor<'s>>(&mut self, pos: Pos, visitor: &mut T) {
+        self.seek(pos as usize);
+        self.lex_rest(visitor);
+    }
+
+    fn lex_rest<T: Visitor<'s>>(&mut self, visitor: &mut T) {
         self.lex_impl(visitor);
+        if let Some(pos) = self.cur_pos() {
+            visitor.eof(self, pos);
+        }
     }
 
     fn lex_impl<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
-        self.consume();
         while self.cur().is_some() {
-            self.consume_comments()?;
             // https://drafts.csswg.org/css-syntax/#consume-token
             match self.cur()? {
-                c if is_white_space(c) => self.consume_space()?,
+                C_SOLIDUS if self.peek() == Some(C_ASTERISK) => self.consume_comment(visitor)?,
+                c if is_white_space(c) => self.consume_white_space(visitor)?,
                 C_QUOTATION_MARK => self.consume_string(visitor, C_QUOTATION_MARK)?,
                 C_NUMBER_SIGN => self.consume_number_sign(visitor)?,
                 C_APOSTROPHE => self.consume_string(visitor, C_APOSTROPHE)?,
                 C_LEFT_PARENTHESIS => self.consume_left_parenthesis(visitor)?,
                 C_RIGHT_PARENTHESIS => self.consume_right_parenthesis(visitor)?,
-                C_PLUS_SIGN => self.consume_plus_sign()?,
+                C_PLUS_SIGN => self.consume_plus_sign(visitor)?,
                 C_COMMA => self.consume_comma(visitor)?,
                 C_HYPHEN_MINUS => self.consume_minus(visitor)?,
                 C_FULL_STOP => self.consume_full_stop(visitor)?,
                 C_COLON => self.consume_potential_pseudo(visitor)?,
                 C_SEMICOLON => self.consume_semicolon(visitor)?,
-                C_LESS_THAN_SIGN => self.consume_less_than_sign()?,
+                C_LESS_THAN_SIGN => self.consume_less_than_sign(visitor)?,
                 C_AT_SIGN => self.consume_at_sign(visitor)?,
-                C_LEFT_SQUARE => self.consume_delim(),
+                C_LEFT_SQUARE => self.consume_left_square(visitor)?,
                 C_REVERSE_SOLIDUS => self.consume_reverse_solidus(visitor)?,
-                C_RIGHT_SQUARE => self.consume_delim(),
+                C_RIGHT_SQUARE => self.consume_right_square(visitor)?,
                 C_LEFT_CURLY => self.consume_left_curly(visitor)?,
                 C_RIGHT_CURLY => self.consume_right_curly(visitor)?,
-                c if is_digit(c) => self.consume_numeric_token()?,
+                c if is_digit(c) => self.consume_numeric_token(visitor)?,
+                C_LOWER_U | C_UPPER_U if self.is_unicode_range_start() => {
+                    self.consume_potential_unicode_range(visitor)?
+                }
                 c if is_ident_start(c) => self.consume_ident_like(visitor)?,
-                _ => self.consume_delim(),
+                _ => self.consume_delim(visitor)?,
             }
         }
         Some(())
     }
 
-    pub fn consume_delim(&mut self) {
+    pub fn consume_delim<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
+        let start = self.cur_pos()?;
         self.consume();
+        visitor.delim(self, start, self.cur_pos()?)
+    }
+
+    pub fn consume_comment<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
+        if self.cur()? != C_SOLIDUS || self.peek()? != C_ASTERISK {
+            return Some(());
+        }
+        let start = self.cur_pos()?;
+        let content_start = start as usize + 2;
+        let finished = match memmem::find(&self.value.as_bytes()[content_start..], b"*/") {
+            Some(i) => {
+                self.seek(content_start + i + 2);
+                Some(())
+            }
+            None => {
+                self.seek(self.value.len());
+                None
+            }
+        };
+        let end = self.cur_pos()?;
+        visitor.comment(self, start, end)?;
+        if finished.is_none() {
+            visitor.unterminated_comment(self, start, end)?;
+        }
+        finished
     }
 
-    pub fn consume_numeric_token(&mut self) -> Option<()> {
+    pub fn consume_white_space<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
+        let start = self.cur_pos()?;
+        // Runs are short in real stylesheets, scanning 8 bytes at a time only pays off for deep
+        // indentation and is slower on bootstrap.css
+        self.skip_until(|bytes| bytes.iter().position(|&b| !is_white_space_byte(b)));
+        let finished = self.cur().map(|_| ());
+        visitor.whitespace(self, start, self.cur_pos()?)?;
+        finished
+    }
+
+    pub fn consume_numeric_token<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
+        let start = self.cur_pos()?;
         self.consume_number()?;
-        let c = self.cur()?;
-        if start_ident_sequence(c, self.peek()?, self.peek2()?) {
-            return self.consume_ident_sequence();
+        let c = self.cur_or_eof();
+        if start_ident_sequence(c, self.peek_or_eof(), self.peek2_or_eof()) {
+            let unit_start = self.cur_pos()?;
+            self.consume_ident_sequence()?;
+            return visitor.dimension(self, start, self.cur_pos()?, unit_start);
         }
         if c == C_PERCENTAGE {
             self.consume();
+            return visitor.percentage(self, start, self.cur_pos()?);
         }
-        Some(())
+        visitor.number(self, start, self.cur_pos()?)
+    }
+
+    fn is_unicode_range_start(&self) -> bool {
+        self.peek() == Some(C_PLUS_SIGN)
+            && self
+                .peek2()
+                .is_some_and(|c| is_hex_digit(c) || c == C_QUESTION_MARK)
+    }
+
+    // https://www.w3.org/TR/2019/CR-css-syntax-3-20190716/#consume-a-unicode-range-token
+    pub fn consume_potential_unicode_range<T: Visitor<'s>>(
+        &mut self,
+        visitor: &mut T,
+    ) -> Option<()> {
+        if visitor.is_selector(self)? {
+            return self.consume_ident_like(visitor);
+        }
+        let start = self.cur_pos()?;
+        self.consume();
+        self.consume();
+        let mut len = 0;
+        while len < 6 && is_hex_digit(self.cur_or_eof()) {
+            self.consume();
+            len += 1;
+        }
+        let mut question_mark = false;
+        while len < 6 && self.cur_or_eof() == C_QUESTION_MARK {
+            self.consume();
+            len += 1;
+            question_mark = true;
+        }
+        if !question_mark && self.cur_or_eof() == C_HYPHEN_MINUS && is_hex_digit(self.peek_or_eof())
+        {
+            self.consume();
+            len = 0;
+            while len < 6 && is_hex_digit(self.cur_or_eof()) {
+                self.consume();
+                len += 1;
+            }
+        }
+        visitor.unicode_range(self, start, self.cur_pos()?)
     }
 
     pub fn consume_number(&mut self) -> Option<()> {
         self.consume();
-        while is_digit(self.cur()?) {
+        while is_digit(self.cur_or_eof()) {
             self.consume();
         }
-        if self.cur()? == C_FULL_STOP && is_digit(self.peek()?) {
+        if self.cur_or_eof() == C_FULL_STOP && is_digit(self.peek_or_eof()) {
             self.consume();
             self.consume();
-            while is_digit(self.cur()?) {
+            while is_digit(self.cur_or_eof()) {
                 self.consume();
             }
         }
-        let c = self.cur()?;
+        let c = self.cur_or_eof();
         if c == C_LOWER_E || c == C_UPPER_E {
-            let c = self.peek()?;
+            let c = self.peek_or_eof();
             if is_digit(c) {
                 self.consume();
             } else if c == C_HYPHEN_MINUS || c == C_PLUS_SIGN {
-                let c = self.peek2()?;
+                let c = self.peek2_or_eof();
                 if is_digit(c) {
                     self.consume();
                     self.consume();
@@ -250,7 +630,7 @@ impl<'s> Lexer<'s> {
             return Some(());
         }
         self.consume();
-        while is_digit(self.cur()?) {
+        while is_digit(self.cur_or_eof()) {
             self.consume();
         }
         Some(())
@@ -258,7 +638,8 @@ impl<'s> Lexer<'s> {
 
     pub fn consume_ident_sequence(&mut self) -> Option<()> {
         loop {
-            let c = self.cur()?;
+            self.skip_ident();
+            let c = self.cur_or_eof();
             if maybe_valid_escape(c) {
                 self.consume();
                 self.consume_escaped()?;
@@ -271,17 +652,17 @@ impl<'s> Lexer<'s> {
     }
 
     pub fn consume_escaped(&mut self) -> Option<()> {
-        if is_hex_digit(self.cur()?) {
+        if is_hex_digit(self.cur_or_eof()) {
             for _ in 1..5 {
                 self.consume();
-                if !is_hex_digit(self.cur()?) {
+                if !is_hex_digit(self.cur_or_eof()) {
                     break;
                 }
             }
-            if is_white_space(self.cur()?) {
+            if is_white_space(self.cur_or_eof()) {
                 self.consume();
             }
-        } else {
+        } else if self.cur().is_some() {
             self.consume();
         }
         Some(())
@@ -290,9 +671,11 @@ impl<'s> Lexer<'s> {
     pub fn consume_ident_like<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
         let start = self.cur_pos()?;
         self.consume_ident_sequence()?;
-        let peek_pos = self.peek_pos()?;
-        if self.cur_pos()? == start + 3 && self.slice(start, peek_pos)?.eq_ignore_ascii_case("url(")
+        if self.cur_pos()? == start + 3
+            && self.cur() == Some(C_LEFT_PARENTHESIS)
+            && self.slice(start, start + 3)?.eq_ignore_ascii_case("url")
         {
+            let peek_pos = self.peek_pos()?;
             self.consume();
             while is_white_space(self.cur()?) {
                 self.consume();
@@ -303,7 +686,7 @@ impl<'s> Lexer<'s> {
             } else {
                 self.consume_url(visitor, start)
             }
-        } else if self.cur()? == C_LEFT_PARENTHESIS {
+        } else if self.cur() == Some(C_LEFT_PARENTHESIS) {
             self.consume();
             visitor.function(self, start, self.cur_pos()?)
         } else {
@@ -318,6 +701,10 @@ impl<'s> Lexer<'s> {
     ) -> Option<()> {
         let content_start = self.cur_pos()?;
         loop {
+            self.skip_until(|bytes| {
+                let end = memchr3(b'\\', b'(', b')', bytes).unwrap_or(bytes.len());
+                find_white_space(&bytes[..end]).or(Some(end))
+            });
             let c = self.cur()?;
             if maybe_valid_escape(c) {
                 self.consume();
@@ -329,7 +716,7 @@ impl<'s> Lexer<'s> {
                     self.consume();
                 }
                 if self.cur()? != C_RIGHT_PARENTHESIS {
-                    return Some(());
+                    return visitor.bad_url(self, start, self.bad_url_end());
                 }
                 self.consume();
                 return visitor.url(self, start, self.cur_pos()?, content_start, content_end);
@@ -338,22 +725,44 @@ impl<'s> Lexer<'s> {
                 self.consume();
                 return visitor.url(self, start, self.cur_pos()?, content_start, content_end);
             } else if c == C_LEFT_PARENTHESIS {
-                return Some(());
+                return visitor.bad_url(self, start, self.bad_url_end());
             } else {
                 self.consume();
             }
         }
     }
 
+    // The bad url isn't consumed, lexing continues where it went bad
+    fn bad_url_end(&self) -> Pos {
+        // https://drafts.csswg.org/css-syntax/#consume-remnants-of-bad-url
+        let mut lexer = self.clone();
+        while let Some(c) = lexer.cur() {
+            lexer.consume();
+            if c == C_RIGHT_PARENTHESIS {
+                break;
+            }
+            if lexer.cur().is_some_and(|c2| are_valid_escape(c, c2)) {
+                lexer.consume_escaped();
+            }
+        }
+        lexer.cur_pos().unwrap_or(self.value.len() as Pos)
+    }
+
     pub fn consume_string<T: Visitor<'s>>(&mut self, visitor: &mut T, end: char) -> Option<()> {
         let start = self.cur_pos()?;
         self.consume();
+        let quote = end as u8;
         loop {
+            self.skip_until(|bytes| {
+                let end = memchr2(quote, b'\\', bytes).unwrap_or(bytes.len());
+                find_new_line(&bytes[..end]).or(Some(end))
+            });
             let c = self.cur()?;
             if c == end {
                 self.consume();
                 break;
             } else if is_new_line(c) {
+                visitor.bad_string(self, start, self.cur_pos()?)?;
                 break;
             } else if c == C_REVERSE_SOLIDUS {
                 self.consume();
@@ -371,20 +780,21 @@ impl<'s> Lexer<'s> {
     }
 
     pub fn consume_number_sign<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
-        let c2 = self.peek()?;
+        let c2 = self.peek_or_eof();
         let start = self.cur_pos()?;
-        if is_ident(c2) || are_valid_escape(c2, self.peek2()?) {
+        if is_ident(c2) || are_valid_escape(c2, self.peek2_or_eof()) {
             self.consume();
             if !visitor.is_selector(self)? {
-                return Some(());
+                self.consume_ident_sequence()?;
+                return visitor.hash(self, start, self.cur_pos()?);
             }
-            if !start_ident_sequence(self.cur()?, self.peek()?, self.peek2()?) {
+            if !start_ident_sequence(self.cur_or_eof(), self.peek_or_eof(), self.peek2_or_eof()) {
                 return visitor.id(self, start, self.cur_pos()?);
             }
             self.consume_ident_sequence()?;
             visitor.id(self, start, self.cur_pos()?)
         } else {
-            self.consume_delim();
+            self.consume();
             visitor.id(self, start, self.cur_pos()?)
         }
     }
@@ -401,13 +811,12 @@ impl<'s> Lexer<'s> {
         visitor.right_parenthesis(self, end - 1, end)
     }
 
-    pub fn consume_plus_sign(&mut self) -> Option<()> {
-        if start_number(self.cur()?, self.peek()?, self.peek2()?) {
-            self.consume_numeric_token()?;
+    pub fn consume_plus_sign<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
+        if start_number(self.cur_or_eof(), self.peek_or_eof(), self.peek2_or_eof()) {
+            self.consume_numeric_token(visitor)
         } else {
-            self.consume_delim();
+            self.consume_delim(visitor)
         }
-        Some(())
     }
 
     pub fn consume_comma<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
@@ -417,35 +826,37 @@ impl<'s> Lexer<'s> {
     }
 
     pub fn consume_minus<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
-        let c = self.cur()?;
-        let c2 = self.peek()?;
-        let c3 = self.peek2()?;
+        let c = self.cur_or_eof();
+        let c2 = self.peek_or_eof();
+        let c3 = self.peek2_or_eof();
         if start_number(c, c2, c3) {
-            self.consume_numeric_token()?;
+            self.consume_numeric_token(visitor)
         } else if c2 == C_HYPHEN_MINUS && c3 == C_GREATER_THAN_SIGN {
+            let start = self.cur_pos()?;
             self.consume();
             self.consume();
+            self.consume();
+            visitor.cdc(self, start, self.cur_pos()?)
         } else if start_ident_sequence(c, c2, c3) {
-            self.consume_ident_like(visitor)?;
+            self.consume_ident_like(visitor)
         } else {
-            self.consume_delim();
+            self.consume_delim(visitor)
         }
-        Some(())
     }
 
     pub fn consume_full_stop<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
-        let c = self.cur()?;
-        let c2 = self.peek()?;
-        let c3 = self.peek2()?;
+        let c = self.cur_or_eof();
+        let c2 = self.peek_or_eof();
+        let c3 = self.peek2_or_eof();
         if start_number(c, c2, c3) {
-            return self.consume_numeric_token();
+            return self.consume_numeric_token(visitor);
         }
         let start = self.cur_pos()?;
         self.consume();
         if !visitor.is_selector(self)? {
-            return Some(());
+            return visitor.delim(self, start, self.cur_pos()?);
         }
-        if !start_ident_sequence(c2, c3, self.peek2()?) {
+        if !start_ident_sequence(c2, c3, self.peek2_or_eof()) {
             return visitor.class(self, start, self.cur_pos()?);
         }
         self.consume_ident_sequence()?;
@@ -456,12 +867,12 @@ impl<'s> Lexer<'s> {
         let start = self.cur_pos()?;
         self.consume();
         if !visitor.is_selector(self)?
-            || !start_ident_sequence(self.cur()?, self.peek()?, self.peek2()?)
+            || !start_ident_sequence(self.cur_or_eof(), self.peek_or_eof(), self.peek2_or_eof())
         {
-            return Some(());
+            return visitor.colon(self, start, self.cur_pos()?);
         }
         self.consume_ident_sequence()?;
-        if self.cur()? == C_LEFT_PARENTHESIS {
+        if self.cur() == Some(C_LEFT_PARENTHESIS) {
             self.consume();
             visitor.pseudo_function(self, start, self.cur_pos()?)
         } else {
@@ -475,33 +886,37 @@ impl<'s> Lexer<'s> {
         visitor.semicolon(self, end - 1, end)
     }
 
-    pub fn consume_less_than_sign(&mut self) -> Option<()> {
+    pub fn consume_less_than_sign<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
+        let start = self.cur_pos()?;
         self.consume();
-        if self.cur()? == '!' && self.peek()? == '-' && self.peek2()? == '-' {
+        if self.cur() == Some(C_EXCLAMATION_MARK)
+            && self.peek() == Some(C_HYPHEN_MINUS)
+            && self.peek2() == Some(C_HYPHEN_MINUS)
+        {
             self.consume();
             self.consume();
             self.consume();
+            return visitor.cdo(self, start, self.cur_pos()?);
         }
-        Some(())
+        visitor.delim(self, start, self.cur_pos()?)
     }
 
     pub fn consume_at_sign<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
         let start = self.cur_pos()?;
         self.consume();
-        if start_ident_sequence(self.cur()?, self.peek()?, self.peek2()?) {
+        if start_ident_sequence(self.cur_or_eof(), self.peek_or_eof(), self.peek2_or_eof()) {
             self.consume_ident_sequence()?;
             return visitor.at_keyword(self, start, self.cur_pos()?);
         }
-        Some(())
+        visitor.delim(self, start, self.cur_pos()?)
     }
 
     pub fn consume_reverse_solidus<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
-        if are_valid_escape(self.cur()?, self.peek()?) {
-            self.consume_ident_like(visitor)?;
+        if are_valid_escape(self.cur_or_eof(), self.peek_or_eof()) {
+            self.consume_ident_like(visitor)
         } else {
-            self.consume_delim();
+            self.consume_delim(visitor)
         }
-        Some(())
     }
 
     pub fn consume_left_curly<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
@@ -515,6 +930,18 @@ impl<'s> Lexer<'s> {
         let end = self.cur_pos()?;
         visitor.right_curly_bracket(self, end - 1, end)
     }
+
+    pub fn consume_left_square<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
+        self.consume();
+        let end = self.cur_pos()?;
+        visitor.left_square_bracket(self, end - 1, end)
+    }
+
+    pub fn consume_right_square<T: Visitor<'s>>(&mut self, visitor: &mut T) -> Option<()> {
+        self.consume();
+        let end = self.cur_pos()?;
+        visitor.right_square_bracket(self, end - 1, end)
+    }
 }
 
 impl<'s, I: Iterator<Item = char>> Lexer<'s, I> {
@@ -524,7 +951,7 @@ impl<'s, I: Iterator<Item = char>> Lexer<'s, I> {
             loop {
                 self.consume();
                 let c = self.cur()?;
-                if c == C_ASTERISK && self.peek()? == C_SOLIDUS {
+                if c == C_ASTERISK && self.peek() == Some(C_SOLIDUS) {
                     self.consume();
                     self.consume();
                     break;
@@ -559,6 +986,27 @@ pub fn is_new_line(c: char) -> bool {
     c == C_LINE_FEED || c == C_CARRIAGE_RETURN || c == C_FORM_FEED
 }
 
+fn is_new_line_byte(b: u8) -> bool {
+    b == b'\n' || b == b'\r' || b == 0x0c
+}
+
+fn is_white_space_byte(b: u8) -> bool {
+    is_new_line_byte(b) || b == b'\t' || b == b' '
+}
+
+fn find_new_line(bytes: &[u8]) -> Option<usize> {
+    memchr3(b'\n', b'\r', 0x0c, bytes)
+}
+
+fn find_white_space(bytes: &[u8]) -> Option<usize> {
+    let end = memchr3(b' ', b'\t', b'\n', bytes).unwrap_or(bytes.len());
+    memchr2(b'\r', 0x0c, &bytes[..end]).or(Some(end).filter(|&end| end < bytes.len()))
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b == b'_' || b == b'-' || b.is_ascii_alphanumeric()
+}
+
 pub fn is_space(c: char) -> bool {
     c == C_TAB || c == C_SPACE
 }
@@ -643,10 +1091,14 @@ mod tests {
         }
 
         pub fn snapshot(&self) -> String {
-            self.results
-                .iter()
-                .map(|(k, v)| format!("{k}: {v}\n"))
-                .collect::<String>()
+            let mut s = String::new();
+            for (k, v) in &self.results {
+                s += k;
+                s += ": ";
+                s += v;
+                s += "\n";
+            }
+            s
         }
     }
 
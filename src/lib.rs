@@ -1,6 +1,40 @@
+mod assets;
+mod base64;
+mod bundle;
+mod cache;
+mod composes;
+mod convention;
 mod dependencies;
+mod edit;
+mod exports;
+mod fanout;
+mod graph;
+mod hash;
+mod incremental;
+mod json;
 mod lexer;
+mod line_index;
+mod names;
+mod owned;
+mod path;
+mod rebase;
+mod source_map;
+mod stream;
+mod tokenizer;
+mod vars;
 
+pub use assets::data_uri;
+pub use assets::mime_type;
+pub use assets::Asset;
+pub use assets::AssetPipeline;
+pub use assets::FileSystem;
+pub use assets::LoadAsset;
+pub use bundle::Bundler;
+pub use cache::Cache;
+pub use convention::camel_case;
+pub use convention::dashes_camel_case;
+pub use convention::ExportNames;
+pub use convention::ExportsConvention;
 pub use dependencies::Dependency;
 pub use dependencies::LexDependencies;
 pub use dependencies::Mode;
@@ -9,8 +43,56 @@ pub use dependencies::Range;
 pub use dependencies::UrlRangeKind;
 pub use dependencies::Warning;
 pub use dependencies::WarningKind;
+pub use edit::apply_replacements;
+pub use edit::apply_replacements_with_source_map;
+pub use edit::EditBuffer;
+pub use edit::EditError;
+pub use edit::Replacement;
+pub use exports::diff_exports;
+pub use exports::CssModuleExport;
+pub use exports::CssModuleExports;
+pub use exports::CssModuleReference;
+pub use exports::ExportsDiff;
+pub use fanout::Fanout;
+pub use graph::Edge;
+pub use graph::EdgeKind;
+pub use graph::MemoryResolver;
+pub use graph::Module;
+pub use graph::ModuleGraph;
+pub use graph::ModuleId;
+pub use graph::Resolved;
+pub use graph::Resolver;
+pub use incremental::IncrementalLex;
 pub use lexer::Lexer;
 pub use lexer::Pos;
+pub use lexer::Utf8Bytes;
+pub use lexer::Visitor;
+pub use line_index::LineIndex;
+pub use line_index::Location;
+pub use names::LocalName;
+pub use names::LocalNameKind;
+pub use names::LocalNameTemplate;
+pub use names::NameCollision;
+pub use owned::OwnedDependency;
+pub use owned::OwnedWarning;
+pub use owned::OwnedWarningKind;
+pub use rebase::rebase_dependency;
+pub use rebase::rebase_url;
+pub use rebase::rebase_urls;
+pub use rebase::unescape;
+pub use source_map::source_mapping_url;
+pub use source_map::InputSourceMap;
+pub use source_map::LineCol;
+pub use source_map::Mapping;
+pub use source_map::OriginalLocation;
+pub use source_map::SourceMap;
+pub use source_map::SourceMapBuilder;
+pub use stream::lex_dependencies_from_reader;
+pub use stream::StreamLexer;
+pub use tokenizer::Token;
+pub use tokenizer::TokenKind;
+pub use tokenizer::Tokenizer;
+pub use vars::ResolvedVar;
 
 pub trait HandleDependency<'s> {
     fn handle_dependency(&mut self, dependency: Dependency<'s>);
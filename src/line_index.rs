@@ -0,0 +1,177 @@
+use std::fmt::Display;
+
+use crate::Lexer;
+use crate::LineCol;
+use crate::Pos;
+use crate::Range;
+
+// Converts between byte offsets, lines and columns of an input. Lines end at `\n`, `\r\n`, `\r`
+// or `\x0c` like they do for the lexer. Columns are in UTF-16 code units like in source maps and
+// editors, unless the method says UTF-8
+#[derive(Debug, Clone)]
+pub struct LineIndex<'s> {
+    input: &'s str,
+    line_starts: Vec<Pos>,
+    // The JS string index of each line start
+    utf16_line_starts: Vec<u32>,
+}
+
+impl<'s> LineIndex<'s> {
+    pub fn new(input: &'s str) -> Self {
+        let mut line_starts = vec![0];
+        let mut utf16_line_starts = vec![0];
+        let bytes = input.as_bytes();
+        let mut utf16_pos = 0;
+        let mut last = 0;
+        for (i, &b) in bytes.iter().enumerate() {
+            if b == b'\n' || b == b'\x0c' || (b == b'\r' && bytes.get(i + 1) != Some(&b'\n')) {
+                utf16_pos += utf16_len(&input[last..i + 1]);
+                last = i + 1;
+                line_starts.push(last as Pos);
+                utf16_line_starts.push(utf16_pos);
+            }
+        }
+        Self {
+            input,
+            line_starts,
+            utf16_line_starts,
+        }
+    }
+
+    pub fn input(&self) -> &'s str {
+        self.input
+    }
+
+    pub fn line_count(&self) -> u32 {
+        self.line_starts.len() as u32
+    }
+
+    // The range of `line` without its line break
+    pub fn line_range(&self, line: u32) -> Option<Range> {
+        let start = *self.line_starts.get(line as usize)?;
+        let end = match self.line_starts.get(line as usize + 1) {
+            Some(&next) => {
+                let text = &self.input[start as usize..next as usize];
+                next - (text.len() - text.trim_end_matches(['\n', '\r', '\x0c']).len()) as Pos
+            }
+            None => self.input.len() as Pos,
+        };
+        Some(Range::new(start, end))
+    }
+
+    // A position past the end or inside a char is moved back to the start of that char
+    pub fn line_col(&self, pos: Pos) -> LineCol {
+        let (line, line_start, pos) = self.locate(pos);
+        LineCol::new(line, utf16_len(&self.input[line_start..pos]))
+    }
+
+    pub fn line_col_utf8(&self, pos: Pos) -> LineCol {
+        let (line, line_start, pos) = self.locate(pos);
+        LineCol::new(line, (pos - line_start) as u32)
+    }
+
+    // None when the column is past the end of the line or inside a surrogate pair
+    pub fn pos(&self, line_col: LineCol) -> Option<Pos> {
+        let range = self.line_range(line_col.line)?;
+        let text = Lexer::slice_range(self.input, &range)?;
+        Some(range.start + utf16_offset(text, line_col.column)? as Pos)
+    }
+
+    // None when the column is past the end of the line or inside a char
+    pub fn pos_utf8(&self, line_col: LineCol) -> Option<Pos> {
+        let range = self.line_range(line_col.line)?;
+        let pos = range.start.checked_add(line_col.column)?;
+        (pos <= range.end && self.input.is_char_boundary(pos as usize)).then_some(pos)
+    }
+
+    // The index in the input as a JS string
+    pub fn utf16_index(&self, pos: Pos) -> u32 {
+        let (line, line_start, pos) = self.locate(pos);
+        self.utf16_line_starts[line as usize] + utf16_len(&self.input[line_start..pos])
+    }
+
+    // None when the index is past the end of the input or inside a surrogate pair
+    pub fn pos_from_utf16_index(&self, index: u32) -> Option<Pos> {
+        let line = self
+            .utf16_line_starts
+            .partition_point(|&start| start <= index)
+            - 1;
+        let start = self.line_starts[line] as usize;
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.input.len(), |&next| next as usize);
+        let offset = utf16_offset(
+            &self.input[start..end],
+            index - self.utf16_line_starts[line],
+        )?;
+        Some((start + offset) as Pos)
+    }
+
+    fn locate(&self, pos: Pos) -> (u32, usize, usize) {
+        let mut pos = (pos as usize).min(self.input.len());
+        while !self.input.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        let line = self
+            .line_starts
+            .partition_point(|&start| start as usize <= pos)
+            - 1;
+        (line as u32, self.line_starts[line] as usize, pos)
+    }
+}
+
+fn utf16_len(s: &str) -> u32 {
+    if s.is_ascii() {
+        s.len() as u32
+    } else {
+        s.chars().map(|c| c.len_utf16() as u32).sum()
+    }
+}
+
+// The byte offset in `s` after `units` UTF-16 code units
+fn utf16_offset(s: &str, units: u32) -> Option<usize> {
+    let mut count = 0;
+    for (i, c) in s.char_indices() {
+        if count >= units {
+            return (count == units).then_some(i);
+        }
+        count += c.len_utf16() as u32;
+    }
+    (count == units).then_some(s.len())
+}
+
+// A human-readable location, displayed as `file:line:column` with both counting from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location<'a> {
+    pub file: &'a str,
+    pub start: LineCol,
+    pub end: LineCol,
+}
+
+impl Display for Location<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.file,
+            self.start.line + 1,
+            self.start.column + 1
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16_offsets() {
+        assert_eq!(utf16_offset("a😀b", 0), Some(0));
+        assert_eq!(utf16_offset("a😀b", 1), Some(1));
+        assert_eq!(utf16_offset("a😀b", 2), None);
+        assert_eq!(utf16_offset("a😀b", 3), Some(5));
+        assert_eq!(utf16_offset("a😀b", 4), Some(6));
+        assert_eq!(utf16_offset("a😀b", 5), None);
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::hash::fnv1a64;
+use crate::ModuleGraph;
+use crate::ModuleId;
+use crate::OwnedDependency;
+use crate::Range;
+
+const HASH_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+const MAX_BASE64_LENGTH: usize = 11;
+const MAX_HEX_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+enum HashEncoding {
+    Base64,
+    Hex,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Name,
+    Local,
+    Hash(HashEncoding, usize),
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct LocalNameTemplate {
+    parts: Vec<Part>,
+    salt: String,
+}
+
+impl LocalNameTemplate {
+    // Supports `[name]`, `[local]`, `[hash]`, `[hash:base64:N]` and `[hash:hex:N]`
+    pub fn new(template: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while !rest.is_empty() {
+            let part = rest.strip_prefix('[').and_then(|inner| {
+                let end = inner.find(']')?;
+                let part = match &inner[..end] {
+                    "name" => Part::Name,
+                    "local" => Part::Local,
+                    "hash" => Part::Hash(HashEncoding::Base64, 5),
+                    placeholder => {
+                        let (encoding, length) = placeholder
+                            .strip_prefix("hash:")?
+                            .split_once(':')
+                            .unwrap_or((&placeholder[5..], ""));
+                        let (encoding, max) = match encoding {
+                            "base64" => (HashEncoding::Base64, MAX_BASE64_LENGTH),
+                            "hex" => (HashEncoding::Hex, MAX_HEX_LENGTH),
+                            _ => return None,
+                        };
+                        let length = if length.is_empty() {
+                            5
+                        } else {
+                            length.parse().ok()?
+                        };
+                        Part::Hash(encoding, length.clamp(1, max))
+                    }
+                };
+                Some((part, end + 2))
+            });
+            let (part, len) = part.unwrap_or_else(|| {
+                let len = rest[1..].find('[').map(|i| i + 1).unwrap_or(rest.len());
+                (Part::Literal(rest[..len].to_string()), len)
+            });
+            match (parts.last_mut(), part) {
+                (Some(Part::Literal(last)), Part::Literal(literal)) => *last += &literal,
+                (_, part) => parts.push(part),
+            }
+            rest = &rest[len..];
+        }
+        Self {
+            parts,
+            salt: String::new(),
+        }
+    }
+
+    pub fn with_salt(mut self, salt: &str) -> Self {
+        self.salt = salt.to_string();
+        self
+    }
+
+    // Makes every hash one character longer, returns `None` once all hashes are at their
+    // maximum length
+    pub fn with_longer_hash(&self) -> Option<Self> {
+        let mut longer = false;
+        let parts = self
+            .parts
+            .iter()
+            .map(|part| match *part {
+                Part::Hash(encoding, length) => {
+                    let max = match encoding {
+                        HashEncoding::Base64 => MAX_BASE64_LENGTH,
+                        HashEncoding::Hex => MAX_HEX_LENGTH,
+                    };
+                    longer |= length < max;
+                    Part::Hash(encoding, (length + 1).min(max))
+                }
+                ref part => part.clone(),
+            })
+            .collect();
+        longer.then(|| Self {
+            parts,
+            salt: self.salt.clone(),
+        })
+    }
+
+    pub fn render(&self, path: &str, local: &str) -> String {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        let name = file_name
+            .rsplit_once('.')
+            .map(|(stem, _)| stem)
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or(file_name);
+        let hash = fnv1a64(format!("{path}\0{local}\0{}", self.salt).as_bytes());
+        let mut result = String::new();
+        for part in &self.parts {
+            match *part {
+                Part::Literal(ref literal) => result += literal,
+                Part::Name => result += name,
+                Part::Local => result += local,
+                Part::Hash(HashEncoding::Base64, length) => {
+                    for i in 0..length {
+                        let shift = 58 - 6 * i as i32;
+                        let bits = if shift >= 0 {
+                            hash >> shift
+                        } else {
+                            hash << -shift
+                        };
+                        result.push(HASH_CHARS[bits as usize & 0b111111] as char);
+                    }
+                }
+                Part::Hash(HashEncoding::Hex, length) => {
+                    result += &format!("{hash:016x}")[..length];
+                }
+            }
+        }
+        let mut result: String = result
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') || !c.is_ascii() {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let mut chars = result.chars();
+        let first = chars.next();
+        let starts_with_digit = match first {
+            Some('-') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        };
+        if starts_with_digit {
+            result.insert(0, '_');
+        }
+        result
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum LocalNameKind {
+    Class,
+    Id,
+    Keyframes,
+    CounterStyle,
+    FontPalette,
+    CustomProperty,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct LocalName<'g> {
+    pub module: ModuleId,
+    pub local: &'g str,
+    pub range: Range,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct NameCollision<'g> {
+    pub kind: LocalNameKind,
+    pub name: String,
+    pub first: LocalName<'g>,
+    pub second: LocalName<'g>,
+}
+
+fn local_name(dependency: &OwnedDependency) -> Option<(LocalNameKind, &str, &Range)> {
+    Some(match dependency {
+        OwnedDependency::LocalClass { name, range, .. } => {
+            (LocalNameKind::Class, &name[1..], range)
+        }
+        OwnedDependency::LocalId { name, range, .. } => (LocalNameKind::Id, &name[1..], range),
+        OwnedDependency::LocalKeyframesDecl { name, range } => {
+            (LocalNameKind::Keyframes, name, range)
+        }
+        OwnedDependency::LocalCounterStyleDecl { name, range } => {
+            (LocalNameKind::CounterStyle, name, range)
+        }
+        OwnedDependency::LocalFontPaletteDecl { name, range } => {
+            (LocalNameKind::FontPalette, name, range)
+        }
+        OwnedDependency::LocalVarDecl { name, range }
+        | OwnedDependency::LocalPropertyDecl { name, range } => {
+            (LocalNameKind::CustomProperty, name, range)
+        }
+        _ => return None,
+    })
+}
+
+impl ModuleGraph {
+    // Every pair of distinct (module, local name) that generate the same name is reported
+    // against the first one that generated it, ranges point at the first occurrence in each file
+    pub fn find_name_collisions<'g>(
+        &'g self,
+        mut generate_local_name: impl FnMut(&'g str, &'g str) -> String,
+    ) -> Vec<NameCollision<'g>> {
+        let mut generated: HashMap<(LocalNameKind, String), LocalName<'g>> = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut collisions = Vec::new();
+        for (module_id, module) in self.modules() {
+            for dependency in module.dependencies() {
+                let Some((kind, local, range)) = local_name(dependency) else {
+                    continue;
+                };
+                if !seen.insert((kind, module_id, local)) {
+                    continue;
+                }
+                let name = generate_local_name(module.id(), local);
+                let current = LocalName {
+                    module: module_id,
+                    local,
+                    range: range.clone(),
+                };
+                match generated.get(&(kind, name.clone())) {
+                    Some(first) => collisions.push(NameCollision {
+                        kind,
+                        name,
+                        first: first.clone(),
+                        second: current,
+                    }),
+                    None => {
+                        generated.insert((kind, name), current);
+                    }
+                }
+            }
+        }
+        collisions
+    }
+
+    // Retries with longer hashes and then with increasing salts until no collision is left
+    // or `attempts` run out, returning the last template and its collisions
+    pub fn resolve_name_collisions(
+        &self,
+        template: LocalNameTemplate,
+        attempts: usize,
+    ) -> (LocalNameTemplate, Vec<NameCollision<'_>>) {
+        let mut template = template;
+        let mut collisions = self.find_name_collisions(|path, local| template.render(path, local));
+        for attempt in 1..=attempts {
+            if collisions.is_empty() {
+                break;
+            }
+            template = match template.with_longer_hash() {
+                Some(longer) => longer,
+                None => template.with_salt(&attempt.to_string()),
+            };
+            collisions = self.find_name_collisions(|path, local| template.render(path, local));
+        }
+        (template, collisions)
+    }
+}
@@ -0,0 +1,596 @@
+use std::fmt::Display;
+
+use smallvec::SmallVec;
+
+use crate::Dependency;
+use crate::EditError;
+use crate::LineIndex;
+use crate::Location;
+use crate::Range;
+use crate::UrlRangeKind;
+use crate::Warning;
+use crate::WarningKind;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum OwnedDependency {
+    Url {
+        request: String,
+        range: Range,
+        kind: UrlRangeKind,
+    },
+    Import {
+        request: String,
+        range: Range,
+        layer: Option<String>,
+        supports: Option<String>,
+        media: Option<String>,
+    },
+    Replace {
+        content: String,
+        range: Range,
+    },
+    LocalClass {
+        name: String,
+        range: Range,
+        explicit: bool,
+    },
+    LocalId {
+        name: String,
+        range: Range,
+        explicit: bool,
+    },
+    LocalVar {
+        name: String,
+        range: Range,
+        from: Option<String>,
+        from_range: Option<Range>,
+    },
+    LocalVarDecl {
+        name: String,
+        range: Range,
+    },
+    LocalPropertyDecl {
+        name: String,
+        range: Range,
+    },
+    LocalKeyframes {
+        name: String,
+        range: Range,
+    },
+    LocalKeyframesDecl {
+        name: String,
+        range: Range,
+    },
+    LocalCounterStyle {
+        name: String,
+        range: Range,
+    },
+    LocalCounterStyleDecl {
+        name: String,
+        range: Range,
+    },
+    LocalFontPalette {
+        name: String,
+        range: Range,
+    },
+    LocalFontPaletteDecl {
+        name: String,
+        range: Range,
+    },
+    Composes {
+        local_classes: Vec<String>,
+        names: Vec<String>,
+        from: Option<String>,
+        range: Range,
+    },
+    ICSSImportFrom {
+        path: String,
+    },
+    ICSSImportValue {
+        prop: String,
+        value: String,
+    },
+    ICSSExportValue {
+        prop: String,
+        value: String,
+        range: Range,
+    },
+}
+
+impl Dependency<'_> {
+    pub fn into_owned(self) -> OwnedDependency {
+        let s = |s: &str| s.to_string();
+        match self {
+            Dependency::Url {
+                request,
+                range,
+                kind,
+            } => OwnedDependency::Url {
+                request: s(request),
+                range,
+                kind,
+            },
+            Dependency::Import {
+                request,
+                range,
+                layer,
+                supports,
+                media,
+            } => OwnedDependency::Import {
+                request: s(request),
+                range,
+                layer: layer.map(s),
+                supports: supports.map(s),
+                media: media.map(s),
+            },
+            Dependency::Replace { content, range } => OwnedDependency::Replace {
+                content: s(content),
+                range,
+            },
+            Dependency::LocalClass {
+                name,
+                range,
+                explicit,
+            } => OwnedDependency::LocalClass {
+                name: s(name),
+                range,
+                explicit,
+            },
+            Dependency::LocalId {
+                name,
+                range,
+                explicit,
+            } => OwnedDependency::LocalId {
+                name: s(name),
+                range,
+                explicit,
+            },
+            Dependency::LocalVar {
+                name,
+                range,
+                from,
+                from_range,
+            } => OwnedDependency::LocalVar {
+                name: s(name),
+                range,
+                from: from.map(s),
+                from_range,
+            },
+            Dependency::LocalVarDecl { name, range } => OwnedDependency::LocalVarDecl {
+                name: s(name),
+                range,
+            },
+            Dependency::LocalPropertyDecl { name, range } => OwnedDependency::LocalPropertyDecl {
+                name: s(name),
+                range,
+            },
+            Dependency::LocalKeyframes { name, range } => OwnedDependency::LocalKeyframes {
+                name: s(name),
+                range,
+            },
+            Dependency::LocalKeyframesDecl { name, range } => OwnedDependency::LocalKeyframesDecl {
+                name: s(name),
+                range,
+            },
+            Dependency::LocalCounterStyle { name, range } => OwnedDependency::LocalCounterStyle {
+                name: s(name),
+                range,
+            },
+            Dependency::LocalCounterStyleDecl { name, range } => {
+                OwnedDependency::LocalCounterStyleDecl {
+                    name: s(name),
+                    range,
+                }
+            }
+            Dependency::LocalFontPalette { name, range } => OwnedDependency::LocalFontPalette {
+                name: s(name),
+                range,
+            },
+            Dependency::LocalFontPaletteDecl { name, range } => {
+                OwnedDependency::LocalFontPaletteDecl {
+                    name: s(name),
+                    range,
+                }
+            }
+            Dependency::Composes {
+                local_classes,
+                names,
+                from,
+                range,
+            } => OwnedDependency::Composes {
+                local_classes: local_classes.iter().map(|c| s(c)).collect(),
+                names: names.iter().map(|n| s(n)).collect(),
+                from: from.map(s),
+                range,
+            },
+            Dependency::ICSSImportFrom { path } => {
+                OwnedDependency::ICSSImportFrom { path: s(path) }
+            }
+            Dependency::ICSSImportValue { prop, value } => OwnedDependency::ICSSImportValue {
+                prop: s(prop),
+                value: s(value),
+            },
+            Dependency::ICSSExportValue { prop, value, range } => {
+                OwnedDependency::ICSSExportValue {
+                    prop: s(prop),
+                    value: s(value),
+                    range,
+                }
+            }
+        }
+    }
+}
+
+impl OwnedDependency {
+    pub(crate) fn range_mut(&mut self) -> Option<&mut Range> {
+        match self {
+            OwnedDependency::Url { range, .. }
+            | OwnedDependency::Import { range, .. }
+            | OwnedDependency::Replace { range, .. }
+            | OwnedDependency::LocalClass { range, .. }
+            | OwnedDependency::LocalId { range, .. }
+            | OwnedDependency::LocalVar { range, .. }
+            | OwnedDependency::LocalVarDecl { range, .. }
+            | OwnedDependency::LocalPropertyDecl { range, .. }
+            | OwnedDependency::LocalKeyframes { range, .. }
+            | OwnedDependency::LocalKeyframesDecl { range, .. }
+            | OwnedDependency::LocalCounterStyle { range, .. }
+            | OwnedDependency::LocalCounterStyleDecl { range, .. }
+            | OwnedDependency::LocalFontPalette { range, .. }
+            | OwnedDependency::LocalFontPaletteDecl { range, .. }
+            | OwnedDependency::Composes { range, .. }
+            | OwnedDependency::ICSSExportValue { range, .. } => Some(range),
+            OwnedDependency::ICSSImportFrom { .. } | OwnedDependency::ICSSImportValue { .. } => {
+                None
+            }
+        }
+    }
+
+    pub fn as_dependency(&self) -> Dependency<'_> {
+        match self {
+            OwnedDependency::Url {
+                request,
+                range,
+                kind,
+            } => Dependency::Url {
+                request,
+                range: range.clone(),
+                kind: *kind,
+            },
+            OwnedDependency::Import {
+                request,
+                range,
+                layer,
+                supports,
+                media,
+            } => Dependency::Import {
+                request,
+                range: range.clone(),
+                layer: layer.as_deref(),
+                supports: supports.as_deref(),
+                media: media.as_deref(),
+            },
+            OwnedDependency::Replace { content, range } => Dependency::Replace {
+                content,
+                range: range.clone(),
+            },
+            OwnedDependency::LocalClass {
+                name,
+                range,
+                explicit,
+            } => Dependency::LocalClass {
+                name,
+                range: range.clone(),
+                explicit: *explicit,
+            },
+            OwnedDependency::LocalId {
+                name,
+                range,
+                explicit,
+            } => Dependency::LocalId {
+                name,
+                range: range.clone(),
+                explicit: *explicit,
+            },
+            OwnedDependency::LocalVar {
+                name,
+                range,
+                from,
+                from_range,
+            } => Dependency::LocalVar {
+                name,
+                range: range.clone(),
+                from: from.as_deref(),
+                from_range: from_range.clone(),
+            },
+            OwnedDependency::LocalVarDecl { name, range } => Dependency::LocalVarDecl {
+                name,
+                range: range.clone(),
+            },
+            OwnedDependency::LocalPropertyDecl { name, range } => Dependency::LocalPropertyDecl {
+                name,
+                range: range.clone(),
+            },
+            OwnedDependency::LocalKeyframes { name, range } => Dependency::LocalKeyframes {
+                name,
+                range: range.clone(),
+            },
+            OwnedDependency::LocalKeyframesDecl { name, range } => Dependency::LocalKeyframesDecl {
+                name,
+                range: range.clone(),
+            },
+            OwnedDependency::LocalCounterStyle { name, range } => Dependency::LocalCounterStyle {
+                name,
+                range: range.clone(),
+            },
+            OwnedDependency::LocalCounterStyleDecl { name, range } => {
+                Dependency::LocalCounterStyleDecl {
+                    name,
+                    range: range.clone(),
+                }
+            }
+            OwnedDependency::LocalFontPalette { name, range } => Dependency::LocalFontPalette {
+                name,
+                range: range.clone(),
+            },
+            OwnedDependency::LocalFontPaletteDecl { name, range } => {
+                Dependency::LocalFontPaletteDecl {
+                    name,
+                    range: range.clone(),
+                }
+            }
+            OwnedDependency::Composes {
+                local_classes,
+                names,
+                from,
+                range,
+            } => Dependency::Composes {
+                local_classes: local_classes
+                    .iter()
+                    .map(|c| c.as_str())
+                    .collect::<SmallVec<_>>(),
+                names: names.iter().map(|n| n.as_str()).collect::<SmallVec<_>>(),
+                from: from.as_deref(),
+                range: range.clone(),
+            },
+            OwnedDependency::ICSSImportFrom { path } => Dependency::ICSSImportFrom { path },
+            OwnedDependency::ICSSImportValue { prop, value } => {
+                Dependency::ICSSImportValue { prop, value }
+            }
+            OwnedDependency::ICSSExportValue { prop, value, range } => {
+                Dependency::ICSSExportValue {
+                    prop,
+                    value,
+                    range: range.clone(),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct OwnedWarning {
+    pub range: Range,
+    pub kind: OwnedWarningKind,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum OwnedWarningKind {
+    Unexpected {
+        message: String,
+    },
+    DuplicateUrl {
+        when: String,
+    },
+    NamespaceNotSupportedInBundledCss,
+    NotPrecededAtImport,
+    ExpectedUrl {
+        when: String,
+    },
+    ExpectedUrlBefore {
+        when: String,
+    },
+    ExpectedLayerBefore {
+        when: String,
+    },
+    InconsistentModeResult,
+    ExpectedNotInside {
+        pseudo: String,
+    },
+    MissingWhitespace {
+        surrounding: String,
+    },
+    NotPure {
+        message: String,
+    },
+    UnexpectedComposition {
+        message: String,
+    },
+    ExportNameCollision {
+        name: String,
+        other: String,
+        other_range: Range,
+    },
+    MissingComposedName {
+        name: String,
+        from: Option<String>,
+    },
+    DependencyCycle {
+        name: String,
+        modules: usize,
+    },
+    MissingImport {
+        request: String,
+    },
+    InvalidEdit {
+        error: EditError,
+    },
+    MissingAsset {
+        request: String,
+    },
+    MissingVar {
+        name: String,
+        from: String,
+    },
+    BadUrl,
+    BadString,
+    UnterminatedComment,
+    UnclosedBlock {
+        opening: String,
+    },
+    UnexpectedClosingBrace,
+}
+
+impl Warning<'_> {
+    pub fn into_owned(self) -> OwnedWarning {
+        let kind = match *self.kind() {
+            WarningKind::Unexpected { message } => OwnedWarningKind::Unexpected {
+                message: message.to_string(),
+            },
+            WarningKind::DuplicateUrl { when } => OwnedWarningKind::DuplicateUrl {
+                when: when.to_string(),
+            },
+            WarningKind::NamespaceNotSupportedInBundledCss => {
+                OwnedWarningKind::NamespaceNotSupportedInBundledCss
+            }
+            WarningKind::NotPrecededAtImport => OwnedWarningKind::NotPrecededAtImport,
+            WarningKind::ExpectedUrl { when } => OwnedWarningKind::ExpectedUrl {
+                when: when.to_string(),
+            },
+            WarningKind::ExpectedUrlBefore { when } => OwnedWarningKind::ExpectedUrlBefore {
+                when: when.to_string(),
+            },
+            WarningKind::ExpectedLayerBefore { when } => OwnedWarningKind::ExpectedLayerBefore {
+                when: when.to_string(),
+            },
+            WarningKind::InconsistentModeResult => OwnedWarningKind::InconsistentModeResult,
+            WarningKind::ExpectedNotInside { pseudo } => OwnedWarningKind::ExpectedNotInside {
+                pseudo: pseudo.to_string(),
+            },
+            WarningKind::MissingWhitespace { surrounding } => OwnedWarningKind::MissingWhitespace {
+                surrounding: surrounding.to_string(),
+            },
+            WarningKind::NotPure { message } => OwnedWarningKind::NotPure {
+                message: message.to_string(),
+            },
+            WarningKind::UnexpectedComposition { message } => {
+                OwnedWarningKind::UnexpectedComposition {
+                    message: message.to_string(),
+                }
+            }
+            WarningKind::ExportNameCollision {
+                name,
+                other,
+                ref other_range,
+            } => OwnedWarningKind::ExportNameCollision {
+                name: name.to_string(),
+                other: other.to_string(),
+                other_range: other_range.clone(),
+            },
+            WarningKind::MissingComposedName { name, from } => {
+                OwnedWarningKind::MissingComposedName {
+                    name: name.to_string(),
+                    from: from.map(|s| s.to_string()),
+                }
+            }
+            WarningKind::DependencyCycle { name, modules } => OwnedWarningKind::DependencyCycle {
+                name: name.to_string(),
+                modules,
+            },
+            WarningKind::MissingImport { request } => OwnedWarningKind::MissingImport {
+                request: request.to_string(),
+            },
+            WarningKind::InvalidEdit { ref error } => OwnedWarningKind::InvalidEdit {
+                error: error.clone(),
+            },
+            WarningKind::MissingAsset { request } => OwnedWarningKind::MissingAsset {
+                request: request.to_string(),
+            },
+            WarningKind::MissingVar { name, from } => OwnedWarningKind::MissingVar {
+                name: name.to_string(),
+                from: from.to_string(),
+            },
+            WarningKind::BadUrl => OwnedWarningKind::BadUrl,
+            WarningKind::BadString => OwnedWarningKind::BadString,
+            WarningKind::UnterminatedComment => OwnedWarningKind::UnterminatedComment,
+            WarningKind::UnclosedBlock { opening } => OwnedWarningKind::UnclosedBlock {
+                opening: opening.to_string(),
+            },
+            WarningKind::UnexpectedClosingBrace => OwnedWarningKind::UnexpectedClosingBrace,
+        };
+        OwnedWarning {
+            range: self.range().clone(),
+            kind,
+        }
+    }
+}
+
+impl OwnedWarning {
+    pub fn location<'a>(&self, file: &'a str, index: &LineIndex) -> Location<'a> {
+        self.range.location(file, index)
+    }
+
+    pub fn as_warning(&self) -> Warning<'_> {
+        let kind = match &self.kind {
+            OwnedWarningKind::Unexpected { message } => WarningKind::Unexpected { message },
+            OwnedWarningKind::DuplicateUrl { when } => WarningKind::DuplicateUrl { when },
+            OwnedWarningKind::NamespaceNotSupportedInBundledCss => {
+                WarningKind::NamespaceNotSupportedInBundledCss
+            }
+            OwnedWarningKind::NotPrecededAtImport => WarningKind::NotPrecededAtImport,
+            OwnedWarningKind::ExpectedUrl { when } => WarningKind::ExpectedUrl { when },
+            OwnedWarningKind::ExpectedUrlBefore { when } => WarningKind::ExpectedUrlBefore { when },
+            OwnedWarningKind::ExpectedLayerBefore { when } => {
+                WarningKind::ExpectedLayerBefore { when }
+            }
+            OwnedWarningKind::InconsistentModeResult => WarningKind::InconsistentModeResult,
+            OwnedWarningKind::ExpectedNotInside { pseudo } => {
+                WarningKind::ExpectedNotInside { pseudo }
+            }
+            OwnedWarningKind::MissingWhitespace { surrounding } => {
+                WarningKind::MissingWhitespace { surrounding }
+            }
+            OwnedWarningKind::NotPure { message } => WarningKind::NotPure { message },
+            OwnedWarningKind::UnexpectedComposition { message } => {
+                WarningKind::UnexpectedComposition { message }
+            }
+            OwnedWarningKind::ExportNameCollision {
+                name,
+                other,
+                other_range,
+            } => WarningKind::ExportNameCollision {
+                name,
+                other,
+                other_range: other_range.clone(),
+            },
+            OwnedWarningKind::MissingComposedName { name, from } => {
+                WarningKind::MissingComposedName {
+                    name,
+                    from: from.as_deref(),
+                }
+            }
+            OwnedWarningKind::DependencyCycle { name, modules } => WarningKind::DependencyCycle {
+                name,
+                modules: *modules,
+            },
+            OwnedWarningKind::MissingImport { request } => WarningKind::MissingImport { request },
+            OwnedWarningKind::InvalidEdit { error } => WarningKind::InvalidEdit {
+                error: error.clone(),
+            },
+            OwnedWarningKind::MissingAsset { request } => WarningKind::MissingAsset { request },
+            OwnedWarningKind::MissingVar { name, from } => WarningKind::MissingVar { name, from },
+            OwnedWarningKind::BadUrl => WarningKind::BadUrl,
+            OwnedWarningKind::BadString => WarningKind::BadString,
+            OwnedWarningKind::UnterminatedComment => WarningKind::UnterminatedComment,
+            OwnedWarningKind::UnclosedBlock { opening } => WarningKind::UnclosedBlock { opening },
+            OwnedWarningKind::UnexpectedClosingBrace => WarningKind::UnexpectedClosingBrace,
+        };
+        Warning::new(self.range.clone(), kind)
+    }
+}
+
+impl Display for OwnedWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_warning().fmt(f)
+    }
+}
@@ -0,0 +1,83 @@
+pub fn dirname(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(i) => &path[..i + 1],
+        None => "",
+    }
+}
+
+pub fn is_relative(request: &str) -> bool {
+    request.starts_with("./") || request.starts_with("../")
+}
+
+pub fn normalize(path: &str) -> String {
+    let absolute = path.starts_with('/');
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if matches!(segments.last(), Some(last) if *last != "..") {
+                    segments.pop();
+                } else if !absolute {
+                    segments.push("..");
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut result = String::with_capacity(path.len());
+    if absolute {
+        result.push('/');
+    }
+    result += &segments.join("/");
+    result
+}
+
+pub fn join(base: &str, request: &str) -> String {
+    if request.starts_with('/') {
+        return normalize(request);
+    }
+    normalize(&format!("{}{}", dirname(base), request))
+}
+
+pub fn relative(base: &str, path: &str) -> String {
+    let base = normalize(dirname(base));
+    let path = normalize(path);
+    let base: Vec<&str> = base.split('/').filter(|s| !s.is_empty()).collect();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let common = base
+        .iter()
+        .zip(&segments)
+        .take_while(|(a, b)| a == b && **a != "..")
+        .count();
+    let mut result: Vec<&str> = vec![".."; base.len() - common];
+    result.extend(&segments[common..]);
+    result.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_paths() {
+        assert_eq!(join("src/a.css", "./b.css"), "src/b.css");
+        assert_eq!(join("src/a.css", "../b.css"), "b.css");
+        assert_eq!(join("a.css", "../b.css"), "../b.css");
+        assert_eq!(join("/src/a.css", "../../b.css"), "/b.css");
+        assert_eq!(join("src/x/a.css", "/b.css"), "/b.css");
+        assert_eq!(join("src/x/a.css", "./../y/./b.css"), "src/y/b.css");
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            relative("dist/app.css", "theme/img.png"),
+            "../theme/img.png"
+        );
+        assert_eq!(relative("app.css", "theme/img.png"), "theme/img.png");
+        assert_eq!(relative("a/b/c.css", "a/d.png"), "../d.png");
+        assert_eq!(relative("a/b/c.css", "a/b/d.png"), "d.png");
+        assert_eq!(relative("a/c.css", "../d.png"), "../../d.png");
+    }
+}
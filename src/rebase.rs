@@ -0,0 +1,223 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use crate::collect_dependencies;
+use crate::path;
+use crate::Dependency;
+use crate::EditBuffer;
+use crate::Mode;
+use crate::Range;
+use crate::Replacement;
+
+pub fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.peek() {
+            None => {}
+            Some('\n' | '\u{c}') => {
+                chars.next();
+            }
+            Some('\r') => {
+                chars.next();
+                chars.next_if_eq(&'\n');
+            }
+            Some(c) if c.is_ascii_hexdigit() => {
+                let mut code = 0;
+                for _ in 0..6 {
+                    let Some(digit) = chars.peek().and_then(|c| c.to_digit(16)) else {
+                        break;
+                    };
+                    code = code * 16 + digit;
+                    chars.next();
+                }
+                if chars.next_if_eq(&'\r').is_some() {
+                    chars.next_if_eq(&'\n');
+                } else {
+                    chars.next_if(|c| matches!(c, ' ' | '\t' | '\n' | '\u{c}'));
+                }
+                result.push(
+                    char::from_u32(code)
+                        .filter(|&c| c != '\0')
+                        .unwrap_or('\u{fffd}'),
+                );
+            }
+            Some(&c) => {
+                chars.next();
+                result.push(c);
+            }
+        }
+    }
+    Cow::Owned(result)
+}
+
+fn write_hex_escape(out: &mut String, c: char) {
+    let _ = write!(out, "\\{:x} ", c as u32);
+}
+
+fn write_quoted(out: &mut String, value: &str, quote: char) {
+    out.push(quote);
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => write_hex_escape(out, c),
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+}
+
+fn write_unquoted(out: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '\\' | '"' | '\'' | '(' | ')' | ' ' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_whitespace() || c.is_control() => write_hex_escape(out, c),
+            c => out.push(c),
+        }
+    }
+}
+
+// `text` is either a string token or a whole `url(...)` function, the new request is written
+// in the same form
+pub fn rewrite(text: &str, request: &str) -> String {
+    let mut result = String::with_capacity(request.len() + 8);
+    if let Some(quote) = text.chars().next().filter(|c| matches!(c, '"' | '\'')) {
+        write_quoted(&mut result, request, quote);
+        return result;
+    }
+    let Some(open) = text.find('(') else {
+        write_unquoted(&mut result, request);
+        return result;
+    };
+    result += &text[..=open];
+    let inner = text[open + 1..].trim_start();
+    match inner.chars().next().filter(|c| matches!(c, '"' | '\'')) {
+        Some(quote) => write_quoted(&mut result, request, quote),
+        None => write_unquoted(&mut result, request),
+    }
+    result.push(')');
+    result
+}
+
+pub fn is_rebasable(url: &str) -> bool {
+    if url.is_empty() || url.starts_with(['#', '/', '?']) {
+        return false;
+    }
+    let scheme_end = url
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+        .unwrap_or(url.len());
+    !(scheme_end > 0
+        && url.as_bytes()[0].is_ascii_alphabetic()
+        && url[scheme_end..].starts_with(':'))
+}
+
+// Relative urls are resolved against `from` and made relative to `to`, both are paths of
+// stylesheets relative to a common root; returns `None` for urls that stay untouched
+pub fn rebase_url(url: &str, from: &str, to: &str) -> Option<String> {
+    if !is_rebasable(url) {
+        return None;
+    }
+    let (path, suffix) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+    let mut rebased = path::relative(to, &path::join(from, path));
+    if rebased.is_empty() {
+        rebased.push('.');
+    }
+    if path::is_relative(url) && !rebased.starts_with("../") {
+        rebased.insert_str(0, "./");
+    }
+    if path.ends_with('/') && !rebased.ends_with('/') {
+        rebased.push('/');
+    }
+    rebased += suffix;
+    Some(rebased)
+}
+
+fn skip_white_space_and_comments(text: &str, mut pos: usize) -> usize {
+    loop {
+        let rest = &text[pos..];
+        let trimmed = rest.trim_start();
+        pos += rest.len() - trimmed.len();
+        if !trimmed.starts_with("/*") {
+            return pos;
+        }
+        pos += trimmed.find("*/").map(|i| i + 2).unwrap_or(trimmed.len());
+    }
+}
+
+pub(crate) fn import_request_range(text: &str) -> Option<(usize, usize)> {
+    let start = skip_white_space_and_comments(text, "@import".len());
+    let rest = &text[start..];
+    let quote = rest.chars().next()?;
+    let mut chars = rest.char_indices().skip(1);
+    if matches!(quote, '"' | '\'') {
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                c if c == quote => return Some((start, start + i + 1)),
+                _ => {}
+            }
+        }
+        return None;
+    }
+    let mut in_string = None;
+    while let Some((i, c)) = chars.next() {
+        match (c, in_string) {
+            ('\\', _) => {
+                chars.next();
+            }
+            ('"' | '\'', None) => in_string = Some(c),
+            (c, Some(q)) if c == q => in_string = None,
+            (')', None) => return Some((start, start + i + 1)),
+            _ => {}
+        }
+    }
+    None
+}
+
+pub fn rebase_dependency<'s>(
+    input: &'s str,
+    dependency: &Dependency<'s>,
+    from: &str,
+    to: &str,
+) -> Option<Replacement<'s>> {
+    let (request, range) = match dependency {
+        Dependency::Url { request, range, .. } => (*request, range.clone()),
+        Dependency::Import { request, range, .. } => {
+            let text = input.get(range.start as usize..range.end as usize)?;
+            let (start, end) = import_request_range(text)?;
+            let range = Range::new(range.start + start as u32, range.start + end as u32);
+            (*request, range)
+        }
+        _ => return None,
+    };
+    let rebased = rebase_url(&unescape(request), from, to)?;
+    let text = input.get(range.start as usize..range.end as usize)?;
+    Some(Replacement::new(range, rewrite(text, &rebased)))
+}
+
+pub fn rebase_urls(input: &str, mode: Mode, from: &str, to: &str) -> String {
+    let (dependencies, _) = collect_dependencies(input, mode);
+    let mut buffer = EditBuffer::new(input);
+    for dependency in &dependencies {
+        if let Some(replacement) = rebase_dependency(input, dependency, from, to) {
+            let _ = buffer.edit(replacement);
+        }
+    }
+    buffer.to_string()
+}
@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+
+use crate::base64;
+use crate::json;
+use crate::LineIndex;
+use crate::Pos;
+use crate::Range;
+
+pub fn encode_vlq(out: &mut String, value: i64) {
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(base64::CHARS[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+pub fn decode_vlq(input: &[u8], pos: &mut usize) -> Option<i64> {
+    let mut value = 0i64;
+    let mut shift = 0;
+    loop {
+        let digit = base64::decode_char(*input.get(*pos)?)? as i64;
+        *pos += 1;
+        value += (digit & 0b11111) << shift;
+        if digit & 0b100000 == 0 {
+            break;
+        }
+        shift += 5;
+        if shift > 60 {
+            return None;
+        }
+    }
+    Some(if value & 1 == 1 {
+        -(value >> 1)
+    } else {
+        value >> 1
+    })
+}
+
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineCol {
+    pub line: u32,
+    pub column: u32,
+}
+
+impl LineCol {
+    pub fn new(line: u32, column: u32) -> Self {
+        Self { line, column }
+    }
+
+    pub fn advance(&mut self, s: &str) {
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' | '\r' | '\u{c}' => {
+                    self.line += 1;
+                    self.column = 0;
+                }
+                c => self.column += c.len_utf16() as u32,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Mapping {
+    pub generated: LineCol,
+    pub source: u32,
+    pub original: LineCol,
+    pub name: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    file: Option<String>,
+    sources: Vec<String>,
+    sources_content: Vec<Option<String>>,
+    names: Vec<String>,
+    mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    pub fn from_json(json: &str) -> Option<Self> {
+        let value = json::parse(json)?;
+        if value.get("version")?.as_f64()? != 3.0 {
+            return None;
+        }
+        let source_root = value
+            .get("sourceRoot")
+            .and_then(|v| v.as_str())
+            .filter(|root| !root.is_empty());
+        let sources = value
+            .get("sources")?
+            .as_array()?
+            .iter()
+            // A `null` source is unknown, it stays empty instead of becoming the source root
+            .map(|source| match (source.as_str(), source_root) {
+                (None, _) => String::new(),
+                (Some(source), Some(root)) if root.ends_with('/') => format!("{root}{source}"),
+                (Some(source), Some(root)) => format!("{root}/{source}"),
+                (Some(source), None) => source.to_string(),
+            })
+            .collect::<Vec<_>>();
+        let mut sources_content = value
+            .get("sourcesContent")
+            .and_then(|v| v.as_array())
+            .map(|contents| {
+                contents
+                    .iter()
+                    .map(|c| c.as_str().map(|c| c.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        sources_content.resize(sources.len(), None);
+        let names = value
+            .get("names")
+            .and_then(|v| v.as_array())
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|n| n.as_str().unwrap_or_default().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut map = SourceMap {
+            file: value
+                .get("file")
+                .and_then(|v| v.as_str())
+                .map(|f| f.to_string()),
+            sources,
+            sources_content,
+            names,
+            mappings: Vec::new(),
+        };
+        map.decode_mappings(value.get("mappings")?.as_str()?)?;
+        Some(map)
+    }
+
+    pub fn from_data_url(url: &str) -> Option<Self> {
+        let (meta, data) = url.strip_prefix("data:")?.split_once(',')?;
+        if !meta.starts_with("application/json") {
+            return None;
+        }
+        let json = if meta.ends_with(";base64") {
+            String::from_utf8(base64::decode(data)?).ok()?
+        } else {
+            percent_decode(data)?
+        };
+        Self::from_json(&json)
+    }
+
+    fn decode_mappings(&mut self, mappings: &str) -> Option<()> {
+        let input = mappings.as_bytes();
+        let mut pos = 0;
+        let mut generated = LineCol::default();
+        let mut source = 0i64;
+        let mut original_line = 0i64;
+        let mut original_column = 0i64;
+        let mut name = 0i64;
+        while pos < input.len() {
+            match input[pos] {
+                b';' => {
+                    pos += 1;
+                    generated.line += 1;
+                    generated.column = 0;
+                    continue;
+                }
+                b',' => {
+                    pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            generated.column =
+                u32::try_from(generated.column as i64 + decode_vlq(input, &mut pos)?).ok()?;
+            if matches!(input.get(pos), None | Some(b',' | b';')) {
+                continue;
+            }
+            source += decode_vlq(input, &mut pos)?;
+            original_line += decode_vlq(input, &mut pos)?;
+            original_column += decode_vlq(input, &mut pos)?;
+            let has_name = !matches!(input.get(pos), None | Some(b',' | b';'));
+            if has_name {
+                name += decode_vlq(input, &mut pos)?;
+            }
+            if source < 0
+                || source as usize >= self.sources.len()
+                || original_line < 0
+                || original_column < 0
+            {
+                return None;
+            }
+            self.mappings.push(Mapping {
+                generated,
+                source: source as u32,
+                original: LineCol::new(original_line as u32, original_column as u32),
+                name: if has_name && name >= 0 && (name as usize) < self.names.len() {
+                    Some(name as u32)
+                } else {
+                    None
+                },
+            });
+        }
+        self.mappings.sort_by_key(|m| m.generated);
+        Some(())
+    }
+
+    pub fn lookup(&self, generated: LineCol) -> Option<&Mapping> {
+        let index = self
+            .mappings
+            .partition_point(|m| m.generated <= generated)
+            .checked_sub(1)?;
+        let mapping = &self.mappings[index];
+        (mapping.generated.line == generated.line).then_some(mapping)
+    }
+
+    pub fn compose(&self, input: &SourceMap) -> SourceMap {
+        let mut builder = SourceMapBuilder::new(self.file());
+        let mut sources = HashMap::new();
+        for mapping in &self.mappings {
+            let Some(original) = input.lookup(mapping.original) else {
+                continue;
+            };
+            let source = *sources.entry(original.source).or_insert_with(|| {
+                builder.add_source(
+                    &input.sources[original.source as usize],
+                    input.sources_content[original.source as usize].as_deref(),
+                )
+            });
+            let name = mapping
+                .name
+                .map(|n| &self.names[n as usize])
+                .or_else(|| {
+                    // Only inherit the name when pointing at the start of the named token
+                    (original.generated == mapping.original)
+                        .then_some(original.name)
+                        .flatten()
+                        .map(|n| &input.names[n as usize])
+                })
+                .map(|name| builder.add_name(name));
+            builder.add_mapping(mapping.generated, source, original.original, name);
+        }
+        builder.build()
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    pub fn sources_content(&self) -> &[Option<String>] {
+        &self.sources_content
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    pub fn encode_mappings(&self) -> String {
+        let mut out = String::new();
+        let mut line = 0;
+        let mut prev_column = 0i64;
+        let mut prev_source = 0i64;
+        let mut prev_original_line = 0i64;
+        let mut prev_original_column = 0i64;
+        let mut prev_name = 0i64;
+        let mut first_in_line = true;
+        for mapping in &self.mappings {
+            while line < mapping.generated.line {
+                out.push(';');
+                line += 1;
+                prev_column = 0;
+                first_in_line = true;
+            }
+            if !first_in_line {
+                out.push(',');
+            }
+            first_in_line = false;
+            let column = mapping.generated.column as i64;
+            encode_vlq(&mut out, column - prev_column);
+            prev_column = column;
+            let source = mapping.source as i64;
+            encode_vlq(&mut out, source - prev_source);
+            prev_source = source;
+            let original_line = mapping.original.line as i64;
+            encode_vlq(&mut out, original_line - prev_original_line);
+            prev_original_line = original_line;
+            let original_column = mapping.original.column as i64;
+            encode_vlq(&mut out, original_column - prev_original_column);
+            prev_original_column = original_column;
+            if let Some(name) = mapping.name {
+                let name = name as i64;
+                encode_vlq(&mut out, name - prev_name);
+                prev_name = name;
+            }
+        }
+        out
+    }
+
+    pub fn to_data_url(&self) -> String {
+        format!(
+            "data:application/json;charset=utf-8;base64,{}",
+            base64::encode(self.to_json().as_bytes())
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{");
+        let mut first = true;
+        json::write_key(&mut out, &mut first, "version");
+        out.push('3');
+        if let Some(file) = &self.file {
+            json::write_key(&mut out, &mut first, "file");
+            json::write_string(&mut out, file);
+        }
+        json::write_key(&mut out, &mut first, "sources");
+        write_string_array(
+            &mut out,
+            self.sources
+                .iter()
+                .map(|s| Some(s.as_str()).filter(|s| !s.is_empty())),
+        );
+        if self.sources_content.iter().any(|c| c.is_some()) {
+            json::write_key(&mut out, &mut first, "sourcesContent");
+            write_string_array(&mut out, self.sources_content.iter().map(|c| c.as_deref()));
+        }
+        json::write_key(&mut out, &mut first, "names");
+        write_string_array(&mut out, self.names.iter().map(|s| Some(s.as_str())));
+        json::write_key(&mut out, &mut first, "mappings");
+        json::write_string(&mut out, &self.encode_mappings());
+        out.push('}');
+        out
+    }
+}
+
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+pub fn source_mapping_url(css: &str) -> Option<&str> {
+    let start = css.rfind("sourceMappingURL=")?;
+    let prefix = css[..start].trim_end_matches([' ', '\t']);
+    if !prefix.ends_with("/*#") && !prefix.ends_with("/*@") {
+        return None;
+    }
+    let url = &css[start + "sourceMappingURL=".len()..];
+    let end = url
+        .find(|c: char| c.is_ascii_whitespace() || c == '*')
+        .unwrap_or(url.len());
+    Some(&url[..end]).filter(|url| !url.is_empty())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OriginalLocation<'a> {
+    pub source: &'a str,
+    pub position: LineCol,
+    pub name: Option<&'a str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InputSourceMap<'s> {
+    line_index: LineIndex<'s>,
+    map: SourceMap,
+}
+
+impl<'s> InputSourceMap<'s> {
+    pub fn new(input: &'s str, map: SourceMap) -> Self {
+        Self {
+            line_index: LineIndex::new(input),
+            map,
+        }
+    }
+
+    pub fn from_inline(input: &'s str) -> Option<Self> {
+        let map = SourceMap::from_data_url(source_mapping_url(input)?)?;
+        Some(Self::new(input, map))
+    }
+
+    pub fn map(&self) -> &SourceMap {
+        &self.map
+    }
+
+    pub fn line_index(&self) -> &LineIndex<'s> {
+        &self.line_index
+    }
+
+    pub fn line_col(&self, pos: Pos) -> LineCol {
+        self.line_index.line_col(pos)
+    }
+
+    pub fn original_location(&self, range: &Range) -> Option<OriginalLocation<'_>> {
+        let mapping = self.map.lookup(self.line_col(range.start))?;
+        Some(OriginalLocation {
+            source: &self.map.sources[mapping.source as usize],
+            position: mapping.original,
+            name: mapping.name.map(|n| self.map.names[n as usize].as_str()),
+        })
+    }
+
+    pub fn compose(&self, output: &SourceMap) -> SourceMap {
+        output.compose(&self.map)
+    }
+}
+
+fn write_string_array<'a>(out: &mut String, items: impl Iterator<Item = Option<&'a str>>) {
+    out.push('[');
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        match item {
+            Some(item) => json::write_string(out, item),
+            None => out.push_str("null"),
+        }
+    }
+    out.push(']');
+}
+
+#[derive(Debug, Default)]
+pub struct SourceMapBuilder {
+    map: SourceMap,
+    name_indexes: HashMap<String, u32>,
+}
+
+impl SourceMapBuilder {
+    pub fn new(file: Option<&str>) -> Self {
+        Self {
+            map: SourceMap {
+                file: file.map(|f| f.to_string()),
+                ..Default::default()
+            },
+            name_indexes: HashMap::new(),
+        }
+    }
+
+    pub fn add_source(&mut self, source: &str, content: Option<&str>) -> u32 {
+        if let Some(index) = self.map.sources.iter().position(|s| s == source) {
+            if content.is_some() {
+                self.map.sources_content[index] = content.map(|c| c.to_string());
+            }
+            return index as u32;
+        }
+        self.map.sources.push(source.to_string());
+        self.map
+            .sources_content
+            .push(content.map(|c| c.to_string()));
+        self.map.sources.len() as u32 - 1
+    }
+
+    pub fn add_name(&mut self, name: &str) -> u32 {
+        if let Some(index) = self.name_indexes.get(name) {
+            return *index;
+        }
+        let index = self.map.names.len() as u32;
+        self.map.names.push(name.to_string());
+        self.name_indexes.insert(name.to_string(), index);
+        index
+    }
+
+    pub fn add_mapping(
+        &mut self,
+        generated: LineCol,
+        source: u32,
+        original: LineCol,
+        name: Option<u32>,
+    ) {
+        if let Some(last) = self.map.mappings.last_mut() {
+            debug_assert!(
+                last.generated <= generated,
+                "mappings must be added in order"
+            );
+            if last.generated == generated {
+                *last = Mapping {
+                    generated,
+                    source,
+                    original,
+                    name,
+                };
+                return;
+            }
+        }
+        self.map.mappings.push(Mapping {
+            generated,
+            source,
+            original,
+            name,
+        });
+    }
+
+    pub fn build(self) -> SourceMap {
+        self.map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vlq() {
+        let mut out = String::new();
+        for value in [0, 1, -1, 15, 16, -16, 123, 1234567] {
+            encode_vlq(&mut out, value);
+            out.push(' ');
+        }
+        assert_eq!(out, "A C D e gB hB 2H uorrC ");
+    }
+
+    #[test]
+    fn vlq_round_trip() {
+        let mut out = String::new();
+        let values = [0, 1, -1, 15, 16, -16, 123, 1234567, -1234567];
+        for value in values {
+            encode_vlq(&mut out, value);
+        }
+        let mut pos = 0;
+        for value in values {
+            assert_eq!(decode_vlq(out.as_bytes(), &mut pos), Some(value));
+        }
+        assert_eq!(pos, out.len());
+        assert_eq!(decode_vlq(b"g", &mut 0), None);
+    }
+
+    #[test]
+    fn line_col() {
+        let mut pos = LineCol::default();
+        pos.advance("a\r\nb\rc\u{c}d\n😀é");
+        assert_eq!(pos, LineCol::new(4, 3));
+    }
+}
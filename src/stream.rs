@@ -0,0 +1,279 @@
+use std::io;
+use std::io::Read;
+use std::str;
+
+use crate::dependencies::SplitPoint;
+use crate::dependencies::SplitState;
+use crate::Dependency;
+use crate::LexDependencies;
+use crate::Lexer;
+use crate::Mode;
+use crate::OwnedDependency;
+use crate::OwnedWarning;
+use crate::OwnedWarningKind;
+use crate::Pos;
+use crate::Warning;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// Input that has to follow the end of a rule before lexing can stop there, the lexer looks at
+// most 4 chars ahead
+pub(crate) const LOOKAHEAD: usize = 16;
+
+// Lexes input that arrives in chunks. Only the input after the end of the last complete rule is
+// kept, rules in `@media`, `@supports` or `@layer` blocks end there too, so memory is bounded by
+// the size of the largest rule. Dependencies and warnings are owned and their ranges are positions
+// in the whole input
+pub struct StreamLexer<D, W> {
+    mode: Mode,
+    buffer: String,
+    // The start of a char that is split between chunks
+    partial: Vec<u8>,
+    // Position of the buffer in the whole input
+    offset: Pos,
+    // Left behind by the rules before the buffer, positions are in the whole input
+    state: SplitState,
+    // The buffer is only lexed again once it has grown to this length, so a long rule isn't lexed
+    // again for every chunk
+    next_split: usize,
+    handle_dependency: D,
+    handle_warning: W,
+}
+
+impl<D: FnMut(OwnedDependency), W: FnMut(OwnedWarning)> StreamLexer<D, W> {
+    pub fn new(mode: Mode, handle_dependency: D, handle_warning: W) -> Self {
+        let state = LexDependencies::new(|_| {}, |_| {}, mode).split_state();
+        Self {
+            mode,
+            buffer: String::new(),
+            partial: Vec::new(),
+            offset: 0,
+            state,
+            next_split: 0,
+            handle_dependency,
+            handle_warning,
+        }
+    }
+
+    pub fn write(&mut self, chunk: &[u8]) -> io::Result<()> {
+        if self.offset as usize + self.buffer.len() + self.partial.len() + chunk.len()
+            > Pos::MAX as usize
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "input is larger than 4 GiB",
+            ));
+        }
+        self.partial.extend_from_slice(chunk);
+        let valid = match str::from_utf8(&self.partial) {
+            Ok(s) => s.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return Err(invalid_utf8()),
+        };
+        self.buffer
+            .push_str(str::from_utf8(&self.partial[..valid]).unwrap());
+        self.partial.drain(..valid);
+        if self.buffer.len() >= self.next_split {
+            self.split();
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        if !self.partial.is_empty() {
+            return Err(invalid_utf8());
+        }
+        let (dependencies, warnings, _, state) = self.lex(None);
+        // Blocks opened before the buffer and never closed are reported at the start of it
+        let unclosed = state
+            .open_blocks
+            .iter()
+            .zip(&self.state.open_blocks)
+            .take_while(|(&pos, _)| pos == 0)
+            .count();
+        self.emit(dependencies, warnings, unclosed);
+        Ok(())
+    }
+
+    // Lexes the buffer, reports what comes before the end of its last complete rule and drops
+    // that part
+    fn split(&mut self) {
+        let limit = self.buffer.len().saturating_sub(LOOKAHEAD) as Pos;
+        let (mut dependencies, mut warnings, split, _) = self.lex(Some(limit));
+        if let Some(split) = split {
+            dependencies.truncate(split.dependencies);
+            warnings.truncate(split.warnings);
+            self.emit(dependencies, warnings, 0);
+            self.state = self.absolute_state(split.state);
+            self.buffer.drain(..split.end as usize);
+            self.offset += split.end;
+        }
+        self.next_split = self.buffer.len() * 2;
+    }
+
+    fn lex(
+        &self,
+        limit: Option<Pos>,
+    ) -> (
+        Vec<OwnedDependency>,
+        Vec<OwnedWarning>,
+        Option<SplitPoint>,
+        SplitState,
+    ) {
+        let mut dependencies = Vec::new();
+        let mut warnings = Vec::new();
+        let mut visitor = LexDependencies::new(
+            |dependency: Dependency| dependencies.push(dependency.into_owned()),
+            |warning: Warning| warnings.push(warning.into_owned()),
+            self.mode,
+        );
+        // Positions before the buffer are 0
+        visitor.set_split_state(SplitState {
+            pure_global: self.state.pure_global.map(|_| 0),
+            open_blocks: self.state.open_blocks.iter().map(|_| 0).collect(),
+            ..self.state.clone()
+        });
+        if let Some(limit) = limit {
+            visitor.record_splits(limit);
+        }
+        Lexer::new(&self.buffer).lex(&mut visitor);
+        let state = visitor.split_state();
+        let split = visitor.into_splits().0.pop();
+        (dependencies, warnings, split, state)
+    }
+
+    fn absolute_state(&self, state: SplitState) -> SplitState {
+        let offset = self.offset;
+        SplitState {
+            pure_global: state.pure_global.map(|pos| match self.state.pure_global {
+                Some(start) if pos == 0 => start,
+                _ => pos + offset,
+            }),
+            open_blocks: state
+                .open_blocks
+                .iter()
+                .enumerate()
+                .map(|(i, &pos)| match self.state.open_blocks.get(i) {
+                    Some(&start) if pos == 0 => start,
+                    _ => pos + offset,
+                })
+                .collect(),
+            ..state
+        }
+    }
+
+    // Reports what was lexed from the buffer with positions in the whole input, the first
+    // `unclosed` blocks reported as never closed were opened before the buffer
+    fn emit(
+        &mut self,
+        dependencies: Vec<OwnedDependency>,
+        warnings: Vec<OwnedWarning>,
+        unclosed: usize,
+    ) {
+        let offset = self.offset;
+        for mut dependency in dependencies {
+            if let Some(range) = dependency.range_mut() {
+                range.start += offset;
+                range.end += offset;
+            }
+            (self.handle_dependency)(dependency);
+        }
+        let mut open_blocks = self.state.open_blocks[..unclosed].iter();
+        for mut warning in warnings {
+            match (&warning.kind, self.state.pure_global) {
+                // The selector may start before the buffer, when it started right after the rules
+                // before it
+                (OwnedWarningKind::NotPure { .. }, Some(start)) if warning.range.start == 0 => {
+                    warning.range.start = start;
+                    warning.range.end += offset;
+                }
+                (OwnedWarningKind::UnclosedBlock { opening }, _)
+                    if warning.range.start == 0 && opening == "{" && open_blocks.len() > 0 =>
+                {
+                    let start = *open_blocks.next().unwrap();
+                    warning.range.start = start;
+                    warning.range.end = start + 1;
+                }
+                _ => {
+                    warning.range.start += offset;
+                    warning.range.end += offset;
+                }
+            }
+            (self.handle_warning)(warning);
+        }
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "input is not valid UTF-8")
+}
+
+// Lexes the input read from `reader` with a `StreamLexer`
+pub fn lex_dependencies_from_reader(
+    mut reader: impl Read,
+    mode: Mode,
+    handle_dependency: impl FnMut(OwnedDependency),
+    handle_warning: impl FnMut(OwnedWarning),
+) -> io::Result<()> {
+    let mut lexer = StreamLexer::new(mode, handle_dependency, handle_warning);
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => return lexer.finish(),
+            Ok(len) => lexer.write(&chunk[..len])?,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_buffer() {
+        let rule = ".a { color: red; background: url(./a.png) }\n";
+        for mode in [Mode::Local, Mode::Css] {
+            // Rules in a block that wraps the whole input are split too
+            for (prefix, suffix) in [("", ""), ("@media print { @layer base {\n", "} }\n")] {
+                let mut count = 0;
+                let mut warnings = Vec::new();
+                let mut lexer = StreamLexer::new(mode, |_| count += 1, |w| warnings.push(w));
+                let mut max_buffer = 0;
+                lexer.write(prefix.as_bytes()).unwrap();
+                for _ in 0..2000 {
+                    lexer.write(rule.repeat(10).as_bytes()).unwrap();
+                    max_buffer = max_buffer.max(lexer.buffer.len());
+                }
+                lexer.write(suffix.as_bytes()).unwrap();
+                lexer.finish().unwrap();
+                assert!(count >= 2000 * 10, "{count}");
+                assert!(warnings.is_empty(), "{warnings:?}");
+                assert!(
+                    max_buffer < rule.len() * 40,
+                    "{mode:?} {prefix:?} {max_buffer}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unclosed_blocks_before_the_buffer() {
+        let input = format!(
+            "@media print {{ .a {{}} @layer a {{\n{}",
+            ".b {}\n".repeat(1000)
+        );
+        let mut warnings = Vec::new();
+        let mut lexer = StreamLexer::new(Mode::Local, |_| {}, |w| warnings.push(w));
+        for chunk in input.as_bytes().chunks(100) {
+            lexer.write(chunk).unwrap();
+        }
+        assert!(lexer.offset > 0);
+        lexer.finish().unwrap();
+        let (_, expected) = crate::collect_dependencies(&input, Mode::Local);
+        let expected: Vec<_> = expected.into_iter().map(|w| w.into_owned()).collect();
+        assert_eq!(warnings, expected);
+        assert_eq!(warnings.len(), 2);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::lexer::are_valid_escape;
+use crate::lexer::is_digit;
+use crate::lexer::is_ident;
+use crate::lexer::is_white_space;
+use crate::lexer::start_ident_sequence;
+use crate::lexer::start_number;
+use crate::lexer::C_APOSTROPHE;
+use crate::lexer::C_ASTERISK;
+use crate::lexer::C_AT_SIGN;
+use crate::lexer::C_COLON;
+use crate::lexer::C_COMMA;
+use crate::lexer::C_FULL_STOP;
+use crate::lexer::C_HYPHEN_MINUS;
+use crate::lexer::C_LEFT_CURLY;
+use crate::lexer::C_LEFT_PARENTHESIS;
+use crate::lexer::C_LEFT_SQUARE;
+use crate::lexer::C_LESS_THAN_SIGN;
+use crate::lexer::C_NUMBER_SIGN;
+use crate::lexer::C_PERCENTAGE;
+use crate::lexer::C_PLUS_SIGN;
+use crate::lexer::C_QUOTATION_MARK;
+use crate::lexer::C_REVERSE_SOLIDUS;
+use crate::lexer::C_RIGHT_CURLY;
+use crate::lexer::C_RIGHT_PARENTHESIS;
+use crate::lexer::C_RIGHT_SQUARE;
+use crate::lexer::C_SEMICOLON;
+use crate::lexer::C_SOLIDUS;
+use crate::Lexer;
+use crate::Pos;
+use crate::Range;
+use crate::Visitor;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum TokenKind {
+    Ident,
+    Function,
+    AtKeyword,
+    Hash,
+    String,
+    BadString,
+    Url,
+    BadUrl,
+    Number,
+    Percentage,
+    Dimension,
+    Delim,
+    Whitespace,
+    Comment,
+    Colon,
+    Semicolon,
+    Comma,
+    Cdo,
+    Cdc,
+    LeftParenthesis,
+    RightParenthesis,
+    LeftSquareBracket,
+    RightSquareBracket,
+    LeftCurlyBracket,
+    RightCurlyBracket,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Token<'s> {
+    pub kind: TokenKind,
+    pub range: Range,
+    pub text: &'s str,
+}
+
+#[derive(Default)]
+struct LastToken {
+    kind: Option<TokenKind>,
+}
+
+impl<'s> Visitor<'s> for LastToken {
+    fn function(&mut self, _: &mut Lexer<'s>, _: Pos, _: Pos) -> Option<()> {
+        self.kind = Some(TokenKind::Function);
+        Some(())
+    }
+
+    fn ident(&mut self, _: &mut Lexer<'s>, _: Pos, _: Pos) -> Option<()> {
+        self.kind = Some(TokenKind::Ident);
+        Some(())
+    }
+
+    fn url(&mut self, _: &mut Lexer<'s>, _: Pos, _: Pos, _: Pos, _: Pos) -> Option<()> {
+        self.kind = Some(TokenKind::Url);
+        Some(())
+    }
+
+    fn string(&mut self, _: &mut Lexer<'s>, _: Pos, _: Pos) -> Option<()> {
+        // A bad string is reported before its `string` callback
+        self.kind.get_or_insert(TokenKind::String);
+        Some(())
+    }
+
+    fn bad_string(&mut self, _: &mut Lexer<'s>, _: Pos, _: Pos) -> Option<()> {
+        self.kind = Some(TokenKind::BadString);
+        Some(())
+    }
+}
+
+// Yields every token of the input, the ranges of consecutive tokens are adjacent and together
+// cover the whole input. Whitespace after `url(` is part of the function token when the url is
+// quoted. Clone the tokenizer for arbitrary lookahead
+#[derive(Debug, Clone)]
+pub struct Tokenizer<'s> {
+    lexer: Lexer<'s>,
+}
+
+impl<'s> Tokenizer<'s> {
+    pub fn new(input: &'s str) -> Self {
+        let mut lexer = Lexer::new(input);
+        lexer.consume();
+        Self { lexer }
+    }
+
+    fn cur(&self) -> char {
+        self.lexer.cur_or_eof()
+    }
+
+    fn peek(&self) -> char {
+        self.lexer.peek_or_eof()
+    }
+
+    fn peek2(&self) -> char {
+        self.lexer.peek2_or_eof()
+    }
+
+    fn consume_numeric(&mut self) -> TokenKind {
+        self.lexer.consume_number();
+        if start_ident_sequence(self.cur(), self.peek(), self.peek2()) {
+            self.lexer.consume_ident_sequence();
+            TokenKind::Dimension
+        } else if self.cur() == C_PERCENTAGE {
+            self.lexer.consume();
+            TokenKind::Percentage
+        } else {
+            TokenKind::Number
+        }
+    }
+
+    fn consume_ident_like(&mut self, start: Pos) -> TokenKind {
+        let mut last = LastToken::default();
+        let finished = self.lexer.consume_ident_like(&mut last).is_some();
+        if let Some(kind) = last.kind {
+            return kind;
+        }
+        let text = self.lexer.slice(start, self.end()).unwrap_or_default();
+        if !text
+            .get(..4)
+            .is_some_and(|s| s.eq_ignore_ascii_case("url("))
+        {
+            return TokenKind::Ident;
+        }
+        if !finished {
+            return TokenKind::Url;
+        }
+        // https://drafts.csswg.org/css-syntax/#consume-remnants-of-bad-url
+        while let Some(c) = self.lexer.cur() {
+            if c == C_RIGHT_PARENTHESIS {
+                self.lexer.consume();
+                break;
+            }
+            self.lexer.consume();
+            if are_valid_escape(c, self.cur()) {
+                self.lexer.consume_escaped();
+            }
+        }
+        TokenKind::BadUrl
+    }
+
+    fn consume_token(&mut self, start: Pos) -> TokenKind {
+        let c = self.cur();
+        if c == C_SOLIDUS && self.peek() == C_ASTERISK {
+            self.lexer.consume_comment(&mut LastToken::default());
+            return TokenKind::Comment;
+        }
+        match c {
+            c if is_white_space(c) => {
+                self.lexer.consume_white_space(&mut LastToken::default());
+                TokenKind::Whitespace
+            }
+            C_QUOTATION_MARK | C_APOSTROPHE => {
+                let mut last = LastToken::default();
+                self.lexer.consume_string(&mut last, c);
+                last.kind.unwrap_or(TokenKind::String)
+            }
+            C_NUMBER_SIGN => {
+                self.lexer.consume();
+                if is_ident(self.cur()) || are_valid_escape(self.cur(), self.peek()) {
+                    self.lexer.consume_ident_sequence();
+                    TokenKind::Hash
+                } else {
+                    TokenKind::Delim
+                }
+            }
+            C_PLUS_SIGN | C_FULL_STOP if start_number(c, self.peek(), self.peek2()) => {
+                self.consume_numeric()
+            }
+            C_HYPHEN_MINUS => {
+                if start_number(c, self.peek(), self.peek2()) {
+                    self.consume_numeric()
+                } else if self.peek() == C_HYPHEN_MINUS && self.peek2() == '>' {
+                    self.lexer.consume();
+                    self.lexer.consume();
+                    self.lexer.consume();
+                    TokenKind::Cdc
+                } else if start_ident_sequence(c, self.peek(), self.peek2()) {
+                    self.consume_ident_like(start)
+                } else {
+                    self.lexer.consume();
+                    TokenKind::Delim
+                }
+            }
+            C_LESS_THAN_SIGN if self.peek() == '!' && self.peek2() == C_HYPHEN_MINUS => {
+                let mut lookahead = self.lexer.clone();
+                lookahead.consume();
+                lookahead.consume();
+                lookahead.consume();
+                if lookahead.cur() == Some(C_HYPHEN_MINUS) {
+                    lookahead.consume();
+                    self.lexer = lookahead;
+                    TokenKind::Cdo
+                } else {
+                    self.lexer.consume();
+                    TokenKind::Delim
+                }
+            }
+            C_AT_SIGN => {
+                self.lexer.consume();
+                if start_ident_sequence(self.cur(), self.peek(), self.peek2()) {
+                    self.lexer.consume_ident_sequence();
+                    TokenKind::AtKeyword
+                } else {
+                    TokenKind::Delim
+                }
+            }
+            C_REVERSE_SOLIDUS if are_valid_escape(c, self.peek()) => self.consume_ident_like(start),
+            c if is_digit(c) => self.consume_numeric(),
+            c if start_ident_sequence(c, self.peek(), self.peek2()) => {
+                self.consume_ident_like(start)
+            }
+            c => {
+                self.lexer.consume();
+                match c {
+                    C_COLON => TokenKind::Colon,
+                    C_SEMICOLON => TokenKind::Semicolon,
+                    C_COMMA => TokenKind::Comma,
+                    C_LEFT_PARENTHESIS => TokenKind::LeftParenthesis,
+                    C_RIGHT_PARENTHESIS => TokenKind::RightParenthesis,
+                    C_LEFT_SQUARE => TokenKind::LeftSquareBracket,
+                    C_RIGHT_SQUARE => TokenKind::RightSquareBracket,
+                    C_LEFT_CURLY => TokenKind::LeftCurlyBracket,
+                    C_RIGHT_CURLY => TokenKind::RightCurlyBracket,
+                    _ => TokenKind::Delim,
+                }
+            }
+        }
+    }
+
+    fn end(&self) -> Pos {
+        self.lexer.cur_pos().unwrap_or_default()
+    }
+}
+
+impl<'s> Iterator for Tokenizer<'s> {
+    type Item = Token<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lexer.cur()?;
+        let start = self.end();
+        let kind = self.consume_token(start);
+        let end = self.end();
+        Some(Token {
+            kind,
+            range: Range::new(start, end),
+            text: self.lexer.slice(start, end)?,
+        })
+    }
+}
@@ -0,0 +1,78 @@
+use crate::exports::unquote;
+use crate::ModuleGraph;
+use crate::ModuleId;
+use crate::OwnedDependency;
+use crate::Range;
+use crate::Warning;
+use crate::WarningKind;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct ResolvedVar<'g> {
+    pub range: Range,
+    pub name: &'g str,
+    pub module: Option<ModuleId>,
+    pub resolved: String,
+}
+
+impl ModuleGraph {
+    fn declares_var(&self, module_id: ModuleId, name: &str) -> bool {
+        self.module(module_id).dependencies().iter().any(|d| {
+            matches!(
+                d,
+                OwnedDependency::LocalVarDecl { name: n, .. }
+                | OwnedDependency::LocalPropertyDecl { name: n, .. } if n == name
+            )
+        })
+    }
+
+    // The range of each resolved var covers both the name and the `from` clause, so replacing
+    // it with `resolved` leaves a plain `var(--name)`; `from global` keeps the name unscoped
+    pub fn resolve_vars<'g>(
+        &'g self,
+        module_id: ModuleId,
+        mut generate_local_name: impl FnMut(&'g str, &'g str) -> String,
+        mut handle_warning: impl FnMut(ModuleId, Warning<'g>),
+    ) -> Vec<ResolvedVar<'g>> {
+        let module = self.module(module_id);
+        let mut result = Vec::new();
+        for (index, dependency) in module.dependencies().iter().enumerate() {
+            let OwnedDependency::LocalVar {
+                name,
+                range,
+                from: Some(from),
+                from_range: Some(from_range),
+            } = dependency
+            else {
+                continue;
+            };
+            let range = Range::new(range.start, from_range.end);
+            let (target, resolved) = match unquote(from) {
+                "global" => (None, format!("--{name}")),
+                from => {
+                    let target = module
+                        .edges()
+                        .iter()
+                        .find(|edge| edge.dependency == index)
+                        .and_then(|edge| edge.target)
+                        .filter(|&target| self.declares_var(target, name));
+                    let Some(target) = target else {
+                        handle_warning(
+                            module_id,
+                            Warning::new(range, WarningKind::MissingVar { name, from }),
+                        );
+                        continue;
+                    };
+                    let id = self.module(target).id();
+                    (Some(target), format!("--{}", generate_local_name(id, name)))
+                }
+            };
+            result.push(ResolvedVar {
+                range,
+                name,
+                module: target,
+                resolved,
+            });
+        }
+        result
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::fs;
+
+use css_module_lexer::data_uri;
+use css_module_lexer::AssetPipeline;
+use css_module_lexer::FileSystem;
+use css_module_lexer::Mode;
+use css_module_lexer::Warning;
+use indoc::indoc;
+use similar_asserts::assert_eq;
+
+fn files() -> HashMap<&'static str, Vec<u8>> {
+    HashMap::from([
+        (
+            "theme/icon.svg",
+            b"<svg xmlns=\"http://www.w3.org/2000/svg\"><path d=\"M0 0\"/></svg>\n".to_vec(),
+        ),
+        ("theme/dot.png", vec![0x89, b'P', b'N', b'G']),
+        ("theme/photo.jpg", vec![0; 128]),
+        ("fonts/a b.woff2", vec![1; 128]),
+    ])
+}
+
+#[test]
+fn manifest_and_inlining() {
+    let files = files();
+    let mut pipeline = AssetPipeline::new(|path: &str| files.get(path).cloned())
+        .inline_limit(100)
+        .public_path("/static/");
+    let input = indoc! {r#"
+        .a {
+            background: url(./icon.svg), url("dot.png");
+            content: image-set('./photo.jpg' 1x, "./photo.jpg" 2x);
+            cursor: url(data:image/png;base64,AAAA), url(https://example.com/a.png), url(#a);
+        }
+        @font-face {
+            src: url("../fonts/a b.woff2?v=1#iefix") format("woff2"), url(../fonts/missing.woff);
+        }
+    "#};
+    let mut warnings = Vec::new();
+    let output = pipeline.process(input, "theme/button.css", Mode::Css, |warning| {
+        warnings.push(warning)
+    });
+    assert_eq!(
+        output,
+        indoc! {r#"
+            .a {
+                background: url(data:image/svg+xml,%3Csvg%20xmlns=%22http://www.w3.org/2000/svg%22%3E%3Cpath%20d=%22M0%200%22/%3E%3C/svg%3E), url("data:image/png;base64,iVBORw==");
+                content: image-set('/static/photo.8421ae12.jpg' 1x, "/static/photo.8421ae12.jpg" 2x);
+                cursor: url(data:image/png;base64,AAAA), url(https://example.com/a.png), url(#a);
+            }
+            @font-face {
+                src: url("/static/a b.9cb939d5.woff2?v=1#iefix") format("woff2"), url(../fonts/missing.woff);
+            }
+        "#}
+    );
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].to_string(),
+        "Can't resolve '../fonts/missing.woff'"
+    );
+    let range = warnings[0].range();
+    assert_eq!(
+        &input[range.start as usize..range.end as usize],
+        "url(../fonts/missing.woff)"
+    );
+    let names: Vec<_> = pipeline
+        .assets()
+        .iter()
+        .map(|asset| {
+            (
+                asset.source.as_str(),
+                asset.name.as_str(),
+                asset.content.len(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("theme/photo.jpg", "photo.8421ae12.jpg", 128),
+            ("fonts/a b.woff2", "a b.9cb939d5.woff2", 128),
+        ]
+    );
+    assert_eq!(
+        pipeline.manifest_json(),
+        r#"{"theme/photo.jpg":"/static/photo.8421ae12.jpg","fonts/a b.woff2":"/static/a b.9cb939d5.woff2"}"#
+    );
+}
+
+#[test]
+fn data_uris() {
+    assert_eq!(data_uri("a.png", b"foo"), "data:image/png;base64,Zm9v");
+    assert_eq!(
+        data_uri("a.bin", b"foo"),
+        "data:application/octet-stream;base64,Zm9v"
+    );
+    assert_eq!(
+        data_uri("a.SVG", "<svg>\"é#\"</svg>".as_bytes()),
+        "data:image/svg+xml,%3Csvg%3E%22%C3%A9%23%22%3C/svg%3E"
+    );
+}
+
+#[test]
+fn file_system() {
+    let root = std::env::temp_dir().join(format!("css-module-lexer-assets-{}", std::process::id()));
+    fs::create_dir_all(root.join("img")).unwrap();
+    fs::write(root.join("img/a.png"), [1, 2, 3]).unwrap();
+    let mut pipeline = AssetPipeline::new(FileSystem::new(&root));
+    let mut warnings = Vec::new();
+    let output = pipeline.process(
+        ".a { background: url(img/a.png); } .b { background: url(../outside.png); }",
+        "index.css",
+        Mode::Css,
+        |warning: Warning| warnings.push(warning.to_string()),
+    );
+    fs::remove_dir_all(&root).unwrap();
+    let name = &pipeline.assets()[0].name;
+    assert_eq!(
+        output,
+        format!(".a {{ background: url({name}); }} .b {{ background: url(../outside.png); }}")
+    );
+    assert_eq!(pipeline.assets()[0].content, vec![1, 2, 3]);
+    assert_eq!(warnings, vec!["Can't resolve '../outside.png'"]);
+}
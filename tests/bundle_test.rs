@@ -0,0 +1,177 @@
+use css_module_lexer::Bundler;
+use css_module_lexer::MemoryResolver;
+use css_module_lexer::Mode;
+use css_module_lexer::ModuleGraph;
+use indoc::indoc;
+use similar_asserts::assert_eq;
+
+fn bundle(files: &[(&str, &str)]) -> (String, Vec<(String, String)>) {
+    let mut resolver = MemoryResolver::new();
+    for (id, source) in files {
+        resolver.insert(*id, *source);
+    }
+    let mut graph = ModuleGraph::new(Mode::Css);
+    let entry = graph.add_entry(files[0].0, files[0].1, &mut resolver);
+    let mut warnings = Vec::new();
+    let output = Bundler::new(&graph).bundle(entry, |id, warning| {
+        warnings.push((graph.module(id).id().to_string(), warning.to_string()))
+    });
+    (output, warnings)
+}
+
+#[test]
+fn conditions() {
+    let (output, warnings) = bundle(&[
+        (
+            "index.css",
+            indoc! {r#"
+                @import "https://example.com/font.css";
+                @import url("./a.css") layer(base) supports(display: grid) screen and (min-width: 100px);
+                @import "./b.css" layer;
+                .index { color: red; }
+            "#},
+        ),
+        (
+            "a.css",
+            indoc! {r#"
+                @import "./c.css" print;
+                .a { color: red; }
+            "#},
+        ),
+        ("b.css", ".b { color: red; }"),
+        ("c.css", ".c { color: red; }\n"),
+    ]);
+    assert!(warnings.is_empty());
+    assert_eq!(
+        output,
+        indoc! {r#"
+            @import "https://example.com/font.css";
+            @media screen and (min-width: 100px) {
+            @supports (display: grid) {
+            @layer base {
+            @media print {
+            .c { color: red; }
+            }
+            }
+            }
+            }
+            @media screen and (min-width: 100px) {
+            @supports (display: grid) {
+            @layer base {
+            .a { color: red; }
+            }
+            }
+            }
+            @layer {
+            .b { color: red; }
+            }
+            .index { color: red; }
+        "#}
+    );
+}
+
+#[test]
+fn last_import_wins() {
+    let (output, warnings) = bundle(&[
+        (
+            "index.css",
+            indoc! {r#"
+                @import "./a.css";
+                @import "./b.css";
+                @import "./a.css";
+                @import "./a.css" print;
+            "#},
+        ),
+        ("a.css", ".a {}\n"),
+        ("b.css", "@import './a.css';\n.b {}\n"),
+    ]);
+    assert!(warnings.is_empty());
+    assert_eq!(
+        output,
+        indoc! {r#"
+            .b {}
+            .a {}
+            @media print {
+            .a {}
+            }
+        "#}
+    );
+}
+
+#[test]
+fn import_cycle() {
+    let (output, warnings) = bundle(&[
+        ("a.css", "@import './b.css';\n.a {}\n"),
+        ("b.css", "@import './a.css';\n.b {}\n"),
+    ]);
+    assert_eq!(output, ".b {}\n.a {}\n");
+    assert_eq!(
+        warnings,
+        vec![(
+            "b.css".to_string(),
+            "Dependency on './a.css' is circular through 2 modules".to_string()
+        )]
+    );
+}
+
+#[test]
+fn conditions_of_external_imports() {
+    let (output, warnings) = bundle(&[
+        (
+            "index.css",
+            indoc! {r#"
+                @import "https://example.com/a.css" print;
+                @import "./a.css" layer(base) supports(display: grid) screen, print;
+            "#},
+        ),
+        (
+            "a.css",
+            indoc! {r#"
+                @import url("https://example.com/b.css") layer(b) supports(gap: 0) (min-width: 100px);
+                @import "https://example.com/c.css";
+                .a {}
+            "#},
+        ),
+    ]);
+    assert!(warnings.is_empty());
+    assert_eq!(
+        output,
+        indoc! {r#"
+            @import "https://example.com/a.css" print;
+            @import url("https://example.com/b.css") layer(base.b) supports((display: grid) and (gap: 0)) screen and (min-width: 100px), print and (min-width: 100px);
+            @import "https://example.com/c.css" layer(base) supports(display: grid) screen, print;
+            @media screen, print {
+            @supports (display: grid) {
+            @layer base {
+            .a {}
+            }
+            }
+            }
+        "#}
+    );
+}
+
+#[test]
+fn diamond_imports() {
+    let mut files = vec![("0.css".to_string(), String::new())];
+    for i in 0..64 {
+        files.push((
+            format!("{}.css", i * 2 + 1),
+            format!("@import './{}.css';\n", i * 2 + 2),
+        ));
+        files[i * 2].1 = format!(
+            "@import './{}.css';\n@import './{}.css';\n.l{i} {{}}\n",
+            i * 2 + 1,
+            i * 2 + 2
+        );
+        files.push((format!("{}.css", i * 2 + 2), String::new()));
+    }
+    let files: Vec<(&str, &str)> = files
+        .iter()
+        .map(|(id, source)| (id.as_str(), source.as_str()))
+        .collect();
+    let (output, warnings) = bundle(&files);
+    assert!(warnings.is_empty());
+    assert_eq!(output.lines().count(), 64);
+    assert!(output.starts_with(".l63 {}\n"));
+}
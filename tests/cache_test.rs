@@ -0,0 +1,177 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+
+use css_module_lexer::collect_dependencies;
+use css_module_lexer::Cache;
+use css_module_lexer::EditError;
+use css_module_lexer::Mode;
+use css_module_lexer::OwnedDependency;
+use css_module_lexer::OwnedWarning;
+use css_module_lexer::OwnedWarningKind;
+use css_module_lexer::Range;
+use indoc::indoc;
+use similar_asserts::assert_eq;
+
+const INPUT: &str = indoc! {r#"
+    @import url("./base.css") layer(base) supports(display: grid) screen;
+    .a { composes: b c from "./other.css"; color: var(--color from "./vars.css"); }
+    #id { background: url(./img.png); }
+    @keyframes spin {}
+    :export { a: b; }
+    .b { composes: missing from global; }
+    :global(.c :local(.d)) {}
+"#};
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "css-module-lexer-cache-{name}-{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn collect_owned(input: &str, mode: Mode) -> (Vec<OwnedDependency>, Vec<OwnedWarning>) {
+    let (dependencies, warnings) = collect_dependencies(input, mode);
+    (
+        dependencies.into_iter().map(|d| d.into_owned()).collect(),
+        warnings.into_iter().map(|w| w.into_owned()).collect(),
+    )
+}
+
+fn entries(dir: &PathBuf) -> Vec<PathBuf> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    entries
+}
+
+#[test]
+fn round_trip() {
+    let dir = cache_dir("round-trip");
+    let cache = Cache::new(&dir);
+    for mode in [Mode::Local, Mode::Global, Mode::Pure, Mode::Css] {
+        assert_eq!(cache.get(INPUT, mode), None);
+        let expected = collect_owned(INPUT, mode);
+        assert_eq!(cache.collect_dependencies(INPUT, mode), expected);
+        assert_eq!(cache.get(INPUT, mode), Some(expected));
+    }
+    assert_eq!(entries(&dir).len(), 4);
+    let (_, warnings) = collect_owned(INPUT, Mode::Pure);
+    assert!(!warnings.is_empty());
+    let (dependencies, _) = cache.get(INPUT, Mode::Local).unwrap();
+    assert!(dependencies
+        .iter()
+        .any(|d| matches!(d, OwnedDependency::Composes { names, .. } if names.len() == 2)));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn bundle_warnings() {
+    let dir = cache_dir("bundle-warnings");
+    let cache = Cache::new(&dir);
+    let warning = |kind| OwnedWarning {
+        range: Range::new(1, 5),
+        kind,
+    };
+    let warnings = vec![
+        warning(OwnedWarningKind::MissingImport {
+            request: "./a.css".to_string(),
+        }),
+        warning(OwnedWarningKind::InvalidEdit {
+            error: EditError::OutOfBounds {
+                range: Range::new(1, 50),
+            },
+        }),
+        warning(OwnedWarningKind::InvalidEdit {
+            error: EditError::Overlap {
+                range: Range::new(1, 5),
+                existing: Range::new(0, 3),
+            },
+        }),
+    ];
+    cache.put(INPUT, Mode::Css, &[], &warnings).unwrap();
+    assert_eq!(
+        cache.get(INPUT, Mode::Css),
+        Some((Vec::new(), warnings.clone()))
+    );
+    assert_eq!(
+        warnings[2].as_warning().to_string(),
+        "Edit 1..5 overlaps with the existing edit 0..3, the source is kept as it is"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invalidation() {
+    let dir = cache_dir("invalidation");
+    let cache = Cache::new(&dir);
+    cache.collect_dependencies(INPUT, Mode::Local);
+    let path = entries(&dir).pop().unwrap();
+    let bytes = fs::read(&path).unwrap();
+    // The input itself isn't stored
+    assert!(!bytes.windows(INPUT.len()).any(|w| w == INPUT.as_bytes()));
+
+    // Entries written by another crate version are misses
+    let version = env!("CARGO_PKG_VERSION");
+    let mut other = bytes.clone();
+    let at = other
+        .windows(version.len())
+        .position(|w| w == version.as_bytes())
+        .unwrap();
+    other[at] = b'9';
+    fs::write(&path, &other).unwrap();
+    assert_eq!(cache.get(INPUT, Mode::Local), None);
+
+    // So are entries of another format and entries whose stored content hash is another one
+    let mut other = bytes.clone();
+    other[4] += 1;
+    fs::write(&path, &other).unwrap();
+    assert_eq!(cache.get(INPUT, Mode::Local), None);
+    let mut other = bytes.clone();
+    // Magic, format, version and mode come before the hash
+    let at = 4 + 1 + 1 + version.len() + 1;
+    other[at + 31] ^= 1;
+    fs::write(&path, &other).unwrap();
+    assert_eq!(cache.get(INPUT, Mode::Local), None);
+
+    // Truncated and corrupted entries are misses too
+    fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+    assert_eq!(cache.get(INPUT, Mode::Local), None);
+    let mut corrupted = bytes.clone();
+    corrupted.push(0);
+    fs::write(&path, &corrupted).unwrap();
+    assert_eq!(cache.get(INPUT, Mode::Local), None);
+
+    // A miss is rewritten with the current version
+    assert_eq!(
+        cache.collect_dependencies(INPUT, Mode::Local),
+        collect_owned(INPUT, Mode::Local)
+    );
+    assert_eq!(fs::read(&path).unwrap(), bytes);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn concurrent_readers() {
+    let dir = cache_dir("concurrent");
+    let expected = collect_owned(INPUT, Mode::Local);
+    thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                let cache = Cache::new(&dir);
+                for _ in 0..20 {
+                    assert_eq!(cache.collect_dependencies(INPUT, Mode::Local), expected);
+                    if let Some(result) = cache.get(INPUT, Mode::Local) {
+                        assert_eq!(result, expected);
+                    }
+                }
+            });
+        }
+    });
+    assert_eq!(entries(&dir).len(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,141 @@
+use css_module_lexer::MemoryResolver;
+use css_module_lexer::Mode;
+use css_module_lexer::ModuleGraph;
+use indoc::indoc;
+
+fn graph(files: &[(&str, &str)]) -> ModuleGraph {
+    let mut resolver = MemoryResolver::new();
+    for (id, source) in files {
+        resolver.insert(*id, *source);
+    }
+    let mut graph = ModuleGraph::new(Mode::Local);
+    graph.add_entry(files[0].0, files[0].1, &mut resolver);
+    graph
+}
+
+fn generate_local_name(id: &str, local: &str) -> String {
+    format!("{}_{local}", id.trim_end_matches(".css"))
+}
+
+#[test]
+fn transitive_composes() {
+    let graph = graph(&[
+        (
+            "index.css",
+            indoc! {r#"
+                .a {
+                    composes: b c from "./base.css";
+                    composes: g from global;
+                }
+                .d { composes: a e; }
+                .e { composes: c from "./base.css"; }
+            "#},
+        ),
+        (
+            "base.css",
+            indoc! {r#"
+                .b { composes: c; }
+                .c { composes: x from "./other.css"; }
+            "#},
+        ),
+        ("other.css", ".x { color: red; }"),
+    ]);
+    let mut warnings = Vec::new();
+    let classes = graph.resolve_composes(
+        graph.get("index.css").unwrap(),
+        generate_local_name,
+        |id, warning| warnings.push((id, warning)),
+    );
+    assert!(warnings.is_empty(), "{warnings:?}");
+    assert_eq!(
+        classes,
+        vec![
+            ("a", "index_a base_b base_c other_x g".to_string()),
+            (
+                "d",
+                "index_d index_a base_b base_c other_x g index_e".to_string()
+            ),
+            ("e", "index_e base_c other_x".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn missing_names() {
+    let input = indoc! {r#"
+        .a {
+            composes: b from "./base.css";
+            composes: c from "./missing.css";
+            composes: d;
+        }
+    "#};
+    let graph = graph(&[("index.css", input), ("base.css", ".x {}")]);
+    let index = graph.get("index.css").unwrap();
+    let mut warnings = Vec::new();
+    let classes = graph.resolve_composes(index, generate_local_name, |id, warning| {
+        warnings.push((id, warning))
+    });
+    assert_eq!(classes, vec![("a", "index_a".to_string())]);
+    let warnings: Vec<_> = warnings
+        .iter()
+        .map(|(id, warning)| {
+            assert_eq!(*id, index);
+            let range = warning.range();
+            (
+                warning.to_string(),
+                &input[range.start as usize..range.end as usize],
+            )
+        })
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            (
+                "Composed class 'b' is not defined in './base.css'".to_string(),
+                "b from \"./base.css\"",
+            ),
+            (
+                "Composed class 'c' is not defined in './missing.css'".to_string(),
+                "c from \"./missing.css\"",
+            ),
+            ("Composed class 'd' is not defined".to_string(), "d",),
+        ]
+    );
+}
+
+#[test]
+fn cycles() {
+    let graph = graph(&[
+        (
+            "index.css",
+            indoc! {r#"
+                .a { composes: b from "./b.css"; }
+                .self { composes: self; }
+            "#},
+        ),
+        ("b.css", ".b { composes: a from './index.css'; }"),
+    ]);
+    let index = graph.get("index.css").unwrap();
+    let b = graph.get("b.css").unwrap();
+    let mut warnings = Vec::new();
+    let classes = graph.resolve_composes(index, generate_local_name, |id, warning| {
+        warnings.push((id, warning.to_string()))
+    });
+    assert_eq!(
+        classes,
+        vec![
+            ("a", "index_a b_b".to_string()),
+            ("self", "index_self".to_string()),
+        ]
+    );
+    assert_eq!(
+        warnings,
+        vec![
+            (
+                b,
+                "Dependency on 'a' is circular through 2 modules".to_string()
+            ),
+            (index, "Dependency on 'self' is circular".to_string()),
+        ]
+    );
+}
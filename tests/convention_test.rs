@@ -0,0 +1,122 @@
+use css_module_lexer::collect_dependencies;
+use css_module_lexer::ExportNames;
+use css_module_lexer::ExportsConvention;
+use css_module_lexer::Lexer;
+use css_module_lexer::Mode;
+use css_module_lexer::WarningKind;
+use indoc::indoc;
+
+fn convert(convention: ExportsConvention, name: &str) -> Vec<String> {
+    convention
+        .convert(name)
+        .into_iter()
+        .map(|n| n.into_owned())
+        .collect()
+}
+
+#[test]
+fn as_is() {
+    assert_eq!(
+        convert(ExportsConvention::AsIs, "btn-primary"),
+        ["btn-primary"]
+    );
+    assert_eq!(
+        convert(ExportsConvention::AsIs, "btn_primary"),
+        ["btn_primary"]
+    );
+}
+
+#[test]
+fn camel_case() {
+    assert_eq!(
+        convert(ExportsConvention::CamelCase, "btn-primary"),
+        ["btn-primary", "btnPrimary"]
+    );
+    assert_eq!(
+        convert(ExportsConvention::CamelCase, "btn__primary_large"),
+        ["btn__primary_large", "btnPrimaryLarge"]
+    );
+    assert_eq!(
+        convert(ExportsConvention::CamelCase, "btnPrimary"),
+        ["btnPrimary"]
+    );
+    assert_eq!(
+        convert(ExportsConvention::CamelCaseOnly, "-btn-primary"),
+        ["btnPrimary"]
+    );
+    assert_eq!(convert(ExportsConvention::CamelCaseOnly, "a-b-"), ["aB"]);
+}
+
+#[test]
+fn dashes() {
+    assert_eq!(
+        convert(ExportsConvention::Dashes, "btn-primary_large"),
+        ["btn-primary_large", "btnPrimary_large"]
+    );
+    assert_eq!(
+        convert(ExportsConvention::DashesOnly, "btn--primary"),
+        ["btnPrimary"]
+    );
+    assert_eq!(convert(ExportsConvention::DashesOnly, "btn-"), ["btn-"]);
+    assert_eq!(convert(ExportsConvention::DashesOnly, "a-ü"), ["aÜ"]);
+}
+
+#[test]
+fn collision() {
+    let input = indoc! {r#"
+        .a-b { color: red; }
+        .aB { color: blue; }
+        .a-b.c-d { color: green; }
+        #c_d {}
+    "#};
+    let (dependencies, _) = collect_dependencies(input, Mode::Local);
+    let mut warnings = Vec::new();
+    let mut names = ExportNames::new(ExportsConvention::CamelCaseOnly, |w| warnings.push(w));
+    let exported: Vec<String> = dependencies
+        .iter()
+        .filter_map(|d| names.add_dependency(d))
+        .flatten()
+        .map(|n| n.into_owned())
+        .collect();
+    assert_eq!(exported, ["aB", "aB", "aB", "cD", "cD"]);
+    assert_eq!(names.get("aB").unwrap().0, "a-b");
+    drop(names);
+    assert_eq!(warnings.len(), 2);
+    let WarningKind::ExportNameCollision {
+        name,
+        other,
+        other_range,
+    } = warnings[0].kind()
+    else {
+        panic!();
+    };
+    assert_eq!(*name, "aB");
+    assert_eq!(*other, "a-b");
+    assert_eq!(
+        Lexer::slice_range(input, warnings[0].range()).unwrap(),
+        ".aB"
+    );
+    assert_eq!(Lexer::slice_range(input, other_range).unwrap(), ".a-b");
+    assert_eq!(
+        warnings[0].to_string(),
+        "Export name of 'aB' collides with the export name of 'a-b'"
+    );
+    let WarningKind::ExportNameCollision { name, other, .. } = warnings[1].kind() else {
+        panic!();
+    };
+    assert_eq!(*name, "c_d");
+    assert_eq!(*other, "c-d");
+}
+
+#[test]
+fn no_collision_as_is() {
+    let input = ".a-b {} .aB {} .a-b {}";
+    let (dependencies, _) = collect_dependencies(input, Mode::Local);
+    let mut warnings = Vec::new();
+    let mut names = ExportNames::new(ExportsConvention::AsIs, |w| warnings.push(w));
+    for dependency in &dependencies {
+        names.add_dependency(dependency);
+    }
+    drop(names);
+    assert!(warnings.is_empty());
+}
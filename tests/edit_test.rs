@@ -0,0 +1,140 @@
+use css_module_lexer::EditBuffer;
+use css_module_lexer::EditError;
+use css_module_lexer::LineCol;
+use css_module_lexer::Range;
+use css_module_lexer::Replacement;
+
+#[test]
+fn edits() {
+    let input = ".a { color: red; }";
+    let mut buffer = EditBuffer::new(input);
+    assert!(buffer.is_empty());
+    buffer.replace(Range::new(1, 2), "_a").unwrap();
+    buffer.remove(Range::new(5, 16)).unwrap();
+    buffer.insert(5, "display: none;").unwrap();
+    buffer.prepend("/* b */\n");
+    buffer.prepend("/* a */\n");
+    buffer.append("\n/* c */");
+    assert!(!buffer.is_empty());
+    assert_eq!(
+        buffer.to_string(),
+        "/* a */\n/* b */\n._a { display: none; }\n/* c */"
+    );
+    let mut written = Vec::new();
+    buffer.write_to(&mut written).unwrap();
+    assert_eq!(String::from_utf8(written).unwrap(), buffer.to_string());
+}
+
+#[test]
+fn same_start_order() {
+    let input = "abc";
+    let mut buffer = EditBuffer::new(input);
+    buffer.replace(Range::new(1, 2), "B").unwrap();
+    buffer.insert(1, "1").unwrap();
+    buffer.insert(1, "2").unwrap();
+    buffer.insert(2, "3").unwrap();
+    buffer.replace(Range::new(1, 1), "4").unwrap();
+    buffer.insert(3, "5").unwrap();
+    buffer.insert(0, "0").unwrap();
+    assert_eq!(buffer.to_string(), "0a124B3c5");
+}
+
+#[test]
+fn overlap() {
+    let input = ":global(.a) .b";
+    let mut buffer = EditBuffer::new(input);
+    buffer.remove(Range::new(0, 8)).unwrap();
+    assert_eq!(
+        buffer.replace(Range::new(7, 10), "x"),
+        Err(EditError::Overlap {
+            range: Range::new(7, 10),
+            existing: Range::new(0, 8),
+        })
+    );
+    assert_eq!(
+        buffer.insert(4, "x"),
+        Err(EditError::Overlap {
+            range: Range::new(4, 4),
+            existing: Range::new(0, 8),
+        })
+    );
+    assert_eq!(
+        buffer.remove(Range::new(0, 8)),
+        Err(EditError::Overlap {
+            range: Range::new(0, 8),
+            existing: Range::new(0, 8),
+        })
+    );
+    buffer.remove(Range::new(10, 11)).unwrap();
+    buffer.insert(10, "/* end */").unwrap();
+    buffer.insert(8, "/* start */").unwrap();
+    assert_eq!(
+        buffer.replace(Range::new(9, 14), "x"),
+        Err(EditError::Overlap {
+            range: Range::new(9, 14),
+            existing: Range::new(10, 10),
+        })
+    );
+    assert_eq!(
+        buffer
+            .replace(Range::new(2, 12), "x")
+            .unwrap_err()
+            .to_string(),
+        "Edit 2..12 overlaps with the existing edit 0..8"
+    );
+    assert_eq!(buffer.to_string(), "/* start */.a/* end */ .b");
+}
+
+#[test]
+fn out_of_bounds() {
+    let input = "a😀";
+    let mut buffer = EditBuffer::new(input);
+    assert_eq!(
+        buffer.insert(6, "x"),
+        Err(EditError::OutOfBounds {
+            range: Range::new(6, 6),
+        })
+    );
+    assert_eq!(
+        buffer.remove(Range::new(1, 2)),
+        Err(EditError::OutOfBounds {
+            range: Range::new(1, 2),
+        })
+    );
+    assert_eq!(
+        buffer.remove(Range::new(1, 0)),
+        Err(EditError::OutOfBounds {
+            range: Range::new(1, 0),
+        })
+    );
+    buffer.remove(Range::new(1, 5)).unwrap();
+    assert_eq!(buffer.to_string(), "a");
+}
+
+#[test]
+fn source_map_with_intro() {
+    let input = ".a {}\n.b {}";
+    let mut buffer = EditBuffer::new(input);
+    buffer.prepend("@layer base {\n");
+    buffer.append("\n}");
+    buffer
+        .edit(Replacement::new(Range::new(7, 8), "_b").with_name("b"))
+        .unwrap();
+    let (output, map) = buffer.to_string_with_source_map("input.css");
+    assert_eq!(output, "@layer base {\n.a {}\n._b {}\n}");
+    let lc = LineCol::new;
+    let mappings: Vec<_> = map
+        .mappings()
+        .iter()
+        .map(|m| (m.generated, m.original, m.name))
+        .collect();
+    assert_eq!(
+        mappings,
+        [
+            (lc(1, 0), lc(0, 0), None),
+            (lc(2, 0), lc(1, 0), None),
+            (lc(2, 1), lc(1, 1), Some(0)),
+            (lc(2, 3), lc(1, 2), None),
+        ]
+    );
+}
@@ -0,0 +1,210 @@
+use css_module_lexer::collect_dependencies;
+use css_module_lexer::diff_exports;
+use css_module_lexer::CssModuleExports;
+use css_module_lexer::CssModuleReference;
+use css_module_lexer::ExportsConvention;
+use css_module_lexer::ExportsDiff;
+use css_module_lexer::Mode;
+use css_module_lexer::Warning;
+use indoc::indoc;
+
+fn exports(input: &str, convention: ExportsConvention) -> (CssModuleExports, Vec<Warning>) {
+    let (dependencies, mut warnings) = collect_dependencies(input, Mode::Local);
+    let exports = CssModuleExports::new(
+        &dependencies,
+        convention,
+        |name| format!("_input__{name}"),
+        |w| warnings.push(w),
+    );
+    (exports, warnings)
+}
+
+#[test]
+fn postcss_modules_json() {
+    let (exports, warnings) = exports(
+        indoc! {r#"
+            .a { color: red; }
+            .b-c {
+                composes: a;
+                composes: d e from "./other.css";
+                composes: f from global;
+            }
+            #g {}
+            @keyframes h {}
+            :export { primary: #fff; }
+        "#},
+        ExportsConvention::CamelCase,
+    );
+    assert!(warnings.is_empty());
+    let resolve_import = |specifier: &str, name: &str| {
+        assert_eq!(specifier, "./other.css");
+        (name == "d").then(|| format!("_other__{name}"))
+    };
+    similar_asserts::assert_eq!(
+        exports.to_postcss_modules_json(resolve_import),
+        r##"{"a":"_input__a","b-c":"_input__b-c _input__a _other__d f","bC":"_input__b-c _input__a _other__d f","g":"_input__g","h":"_input__h","primary":"#fff"}"##
+    );
+}
+
+#[test]
+fn lightningcss_json() {
+    let (exports, warnings) = exports(
+        indoc! {r#"
+            .a { animation: h 1s; }
+            .b {
+                composes: a c;
+                composes: d from './other.css';
+                composes: global(e);
+                color: var(--x from "./tokens.css");
+                background: var(--y from global);
+            }
+            .c {}
+            @keyframes h {}
+        "#},
+        ExportsConvention::AsIs,
+    );
+    assert!(warnings.is_empty());
+    similar_asserts::assert_eq!(
+        exports.to_lightningcss_json(),
+        concat!(
+            r#"{"a":{"name":"_input__a","composes":[],"isReferenced":true},"#,
+            r#""b":{"name":"_input__b","composes":[{"type":"local","name":"_input__a"},{"type":"local","name":"_input__c"},{"type":"dependency","name":"d","specifier":"./other.css"},{"type":"global","name":"e"}],"isReferenced":false},"#,
+            r#""c":{"name":"_input__c","composes":[],"isReferenced":true},"#,
+            r#""h":{"name":"_input__h","composes":[],"isReferenced":true}}"#,
+        )
+    );
+    similar_asserts::assert_eq!(
+        exports.to_lightningcss_references_json(),
+        r#"{"--x":{"type":"dependency","name":"x","specifier":"./tokens.css"},"--y":{"type":"global","name":"y"}}"#
+    );
+    assert_eq!(
+        exports.get("b").unwrap().composes[3],
+        CssModuleReference::Global { name: "e" }
+    );
+}
+
+#[test]
+fn escape_json() {
+    let (exports, _) = exports(
+        r#":export { "quoted\name": "a"; }"#,
+        ExportsConvention::AsIs,
+    );
+    assert_eq!(
+        exports.to_postcss_modules_json(|_, _| None),
+        r#"{"\"quoted\\name\"":"\"a\""}"#
+    );
+}
+
+#[test]
+fn collision_warning() {
+    let (exports, warnings) = exports(".a-b {} .aB {}", ExportsConvention::CamelCaseOnly);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(exports.iter().count(), 2);
+}
+
+#[test]
+fn export_value_collision_warning() {
+    let input = ".a {} .a {} :export { a: red; b: blue; b: green; }";
+    let (exports, warnings) = exports(input, ExportsConvention::AsIs);
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(
+        warnings[0].to_string(),
+        "Export name of 'a' collides with the export name of 'a'"
+    );
+    assert_eq!(exports.get("a").unwrap().name, "_input__a");
+    assert_eq!(exports.get("b").unwrap().name, "blue");
+}
+
+#[test]
+fn diff() {
+    let old = indoc! {r#"
+        .a { color: red; }
+        .b { composes: a; }
+        .c { composes: x from "./x.css"; }
+        .removed {}
+        @keyframes fade {}
+        :export { primary: blue; }
+    "#};
+    let diff = |new| diff_exports(old, new, Mode::Local, ExportsConvention::AsIs);
+
+    let declarations_only = indoc! {r#"
+        .a { color: blue; margin: 0; }
+        .b { composes: a; padding: 0; }
+        .c { composes: x from "./x.css"; }
+        .removed { display: none; }
+        @keyframes fade { from { opacity: 0; } }
+        :export { primary: blue; }
+    "#};
+    assert!(diff(declarations_only).is_empty());
+
+    let changed = indoc! {r#"
+        .a { color: red; }
+        .b { composes: a c; }
+        .c { composes: y from "./x.css"; }
+        @keyframes fade {}
+        .added { composes: a; }
+        :export { primary: green; }
+    "#};
+    assert_eq!(
+        diff(changed),
+        ExportsDiff {
+            added: vec!["added".to_string()],
+            removed: vec!["removed".to_string()],
+            changed: vec!["b".to_string(), "c".to_string(), "primary".to_string()],
+            ..Default::default()
+        }
+    );
+
+    // Classes and keyframes are renamed in place, an `:export` key keeps its value
+    let renamed = indoc! {r#"
+        .a { color: red; }
+        .b { composes: a; }
+        .c { composes: x from "./x.css"; }
+        .gone {}
+        @keyframes fade-in {}
+        :export { main: blue; }
+    "#};
+    assert_eq!(
+        diff(renamed),
+        ExportsDiff {
+            renamed: vec![
+                ("removed".to_string(), "gone".to_string()),
+                ("fade".to_string(), "fade-in".to_string()),
+                ("primary".to_string(), "main".to_string()),
+            ],
+            ..Default::default()
+        }
+    );
+    let camel_renamed = diff_exports(
+        ".btn-primary { composes: a; } .a {}",
+        ".btn-main { composes: a; } .a {}",
+        Mode::Local,
+        ExportsConvention::CamelCase,
+    );
+    assert_eq!(
+        camel_renamed.renamed,
+        vec![
+            ("btn-primary".to_string(), "btn-main".to_string()),
+            ("btnPrimary".to_string(), "btnMain".to_string()),
+        ]
+    );
+
+    let references = diff_exports(
+        r#".a { color: var(--x from "./x.css"); background: var(--y from global); }"#,
+        r#".a { color: var(--x from "./y.css"); border-color: var(--z from global); }"#,
+        Mode::Local,
+        ExportsConvention::AsIs,
+    );
+    assert_eq!(
+        references.changed_references,
+        vec!["--x".to_string(), "--z".to_string(), "--y".to_string()]
+    );
+
+    let camel = diff_exports(
+        ".foo-bar {}",
+        ".foo-bar {} .baz {}",
+        Mode::Local,
+        ExportsConvention::CamelCase,
+    );
+    assert_eq!(camel.added, vec!["baz".to_string()]);
+}
@@ -0,0 +1,132 @@
+use css_module_lexer::collect_dependencies;
+use css_module_lexer::Mode;
+use css_module_lexer::TokenKind;
+use css_module_lexer::Tokenizer;
+use indoc::indoc;
+
+const BOOTSTRAP: &str = include_str!("../fixtures/bootstrap.css");
+const BOOTSTRAP_MIN: &str = include_str!("../fixtures/bootstrap.min.css");
+
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// Digests of the complete output, they only change when the output of the lexer changes
+fn digest(input: &str) -> String {
+    let mut result = String::new();
+    for mode in [Mode::Local, Mode::Global, Mode::Pure, Mode::Css] {
+        let (dependencies, warnings) = collect_dependencies(input, mode);
+        result += &format!(
+            "{mode:?}: {} dependencies, {} warnings, {:016x}\n",
+            dependencies.len(),
+            warnings.len(),
+            fnv1a64(format!("{dependencies:?}{warnings:?}").as_bytes())
+        );
+    }
+    let tokens: Vec<_> = Tokenizer::new(input).collect();
+    result += &format!(
+        "tokens: {}, {:016x}\n",
+        tokens.len(),
+        fnv1a64(format!("{tokens:?}").as_bytes())
+    );
+    result
+}
+
+#[test]
+fn bootstrap() {
+    similar_asserts::assert_eq!(
+        digest(BOOTSTRAP),
+        indoc! {"
+            Local: 6172 dependencies, 0 warnings, 9d7e616d6cac0daa
+            Global: 23 dependencies, 0 warnings, da8eb91556b34903
+            Pure: 6172 dependencies, 687 warnings, 6664b287e9a8276e
+            Css: 23 dependencies, 0 warnings, da8eb91556b34903
+            tokens: 72282, d65c88537f06aaaa
+        "}
+    );
+}
+
+#[test]
+fn bootstrap_min() {
+    similar_asserts::assert_eq!(
+        digest(BOOTSTRAP_MIN),
+        indoc! {"
+            Local: 6176 dependencies, 0 warnings, f05f2f86252db9ee
+            Global: 23 dependencies, 0 warnings, 3645ead118f0b181
+            Pure: 6176 dependencies, 684 warnings, 185454dbd582e320
+            Css: 23 dependencies, 0 warnings, 3645ead118f0b181
+            tokens: 47093, 2dfa294af0c55680
+        "}
+    );
+}
+
+#[test]
+fn non_ascii() {
+    let input =
+        ".ä\u{80}b, #日本\\31 x { content: \"é\\\"ü\" '😀'; background: url(ö/ß.png) } /* ✓ */";
+    let tokens: Vec<_> = Tokenizer::new(input)
+        .filter(|t| t.kind != TokenKind::Whitespace)
+        .map(|t| format!("{:?} {:?}", t.kind, t.text))
+        .collect();
+    similar_asserts::assert_eq!(
+        tokens.join("\n"),
+        indoc! {r##"
+            Delim "."
+            Ident "ä"
+            Delim "\u{80}"
+            Ident "b"
+            Comma ","
+            Hash "#日本\\31 x"
+            LeftCurlyBracket "{"
+            Ident "content"
+            Colon ":"
+            String "\"é\\\"ü\""
+            String "'😀'"
+            Semicolon ";"
+            Ident "background"
+            Colon ":"
+            Url "url(ö/ß.png)"
+            RightCurlyBracket "}"
+            Comment "/* ✓ */""##}
+    );
+}
+
+#[test]
+fn scanner_stops() {
+    let input = "a \t\u{c}\r\n                    \t b /**** * / ***/ url(a\u{c}) url(b\\)c) url(d(e) url(f\t) \"e\\\"\" \"a\rb\" 'c\u{c}d' /* x";
+    let tokens: Vec<_> = Tokenizer::new(input)
+        .map(|t| format!("{:?} {:?}", t.kind, t.text))
+        .collect();
+    similar_asserts::assert_eq!(
+        tokens.join("\n"),
+        indoc! {r#"
+            Ident "a"
+            Whitespace " \t\u{c}\r\n                    \t "
+            Ident "b"
+            Whitespace " "
+            Comment "/**** * / ***/"
+            Whitespace " "
+            Url "url(a\u{c})"
+            Whitespace " "
+            Url "url(b\\)c)"
+            Whitespace " "
+            BadUrl "url(d(e)"
+            Whitespace " "
+            Url "url(f\t)"
+            Whitespace " "
+            String "\"e\\\"\""
+            Whitespace " "
+            BadString "\"a"
+            Whitespace "\r"
+            Ident "b"
+            BadString "\" 'c"
+            Whitespace "\u{c}"
+            Ident "d"
+            String "' /* x""#}
+    );
+}
@@ -0,0 +1,269 @@
+use css_module_lexer::EdgeKind;
+use css_module_lexer::MemoryResolver;
+use css_module_lexer::Mode;
+use css_module_lexer::ModuleGraph;
+use css_module_lexer::ModuleId;
+use css_module_lexer::OwnedDependency;
+use css_module_lexer::Resolved;
+use indoc::indoc;
+
+fn resolver() -> MemoryResolver {
+    let mut resolver = MemoryResolver::new();
+    resolver
+        .insert(
+            "src/index.css",
+            indoc! {r#"
+                @import "./base.css";
+                :import("./colors.css") {
+                    i__primary: primary;
+                }
+                .a {
+                    composes: b from "./base.css";
+                    composes: c from global;
+                    color: var(--color from "../theme.css");
+                    background: url(./img.png);
+                }
+            "#},
+        )
+        .insert("src/base.css", ".b { color: red; }")
+        .insert(
+            "src/colors.css",
+            indoc! {r#"
+                @import "./base.css";
+                :export {
+                    primary: blue;
+                }
+            "#},
+        )
+        .insert("theme.css", ":root { --color: red; }");
+    resolver
+}
+
+#[test]
+fn edges() {
+    let mut resolver = resolver();
+    let mut graph = ModuleGraph::new(Mode::Local);
+    let entry = graph.add_entry(
+        "src/index.css",
+        resolver.get("src/index.css").unwrap().to_string(),
+        &mut resolver,
+    );
+    assert_eq!(graph.len(), 4);
+    let base = graph.get("src/base.css").unwrap();
+    let colors = graph.get("src/colors.css").unwrap();
+    let theme = graph.get("theme.css").unwrap();
+    let edges: Vec<_> = graph
+        .module(entry)
+        .edges()
+        .iter()
+        .map(|edge| (edge.kind, edge.request.as_str(), edge.target))
+        .collect();
+    assert_eq!(
+        edges,
+        vec![
+            (EdgeKind::Import, "./base.css", Some(base)),
+            (EdgeKind::ICSS, "./colors.css", Some(colors)),
+            (EdgeKind::Composes, "./base.css", Some(base)),
+            (EdgeKind::Var, "../theme.css", Some(theme)),
+            (EdgeKind::Url, "./img.png", None),
+        ]
+    );
+    let composes = &graph.module(entry).edges()[2];
+    assert!(matches!(
+        &graph.module(entry).dependencies()[composes.dependency],
+        OwnedDependency::Composes { names, .. } if names == &["b"]
+    ));
+    assert_eq!(graph.module(base).source(), ".b { color: red; }");
+    assert!(graph.module(base).edges().is_empty());
+
+    let mut dependents: Vec<_> = graph
+        .dependents(base)
+        .map(|(id, edge)| (graph.module(id).id(), edge.kind))
+        .collect();
+    dependents.sort_by_key(|(id, _)| *id);
+    assert_eq!(
+        dependents,
+        vec![
+            ("src/colors.css", EdgeKind::Import),
+            ("src/index.css", EdgeKind::Import),
+            ("src/index.css", EdgeKind::Composes),
+        ]
+    );
+}
+
+#[test]
+fn post_order() {
+    let mut resolver = resolver();
+    let mut graph = ModuleGraph::new(Mode::Local);
+    let entry = graph.add_entry(
+        "src/index.css",
+        resolver.get("src/index.css").unwrap().to_string(),
+        &mut resolver,
+    );
+    let order: Vec<_> = graph
+        .post_order(entry)
+        .into_iter()
+        .map(|id| graph.module(id).id())
+        .collect();
+    assert_eq!(
+        order,
+        vec![
+            "src/base.css",
+            "src/colors.css",
+            "theme.css",
+            "src/index.css"
+        ]
+    );
+}
+
+#[test]
+fn cycles_and_missing_files() {
+    let mut resolver = MemoryResolver::new();
+    resolver
+        .insert("a.css", "@import './b.css';\n@import './missing.css';")
+        .insert("b.css", "@import './a.css';");
+    let mut graph = ModuleGraph::new(Mode::Css);
+    let a = graph.add_entry(
+        "a.css",
+        resolver.get("a.css").unwrap().to_string(),
+        &mut resolver,
+    );
+    let b = graph.get("b.css").unwrap();
+    assert_eq!(graph.len(), 2);
+    assert_eq!(graph.module(a).edges()[0].target, Some(b));
+    assert_eq!(graph.module(a).edges()[1].target, None);
+    assert_eq!(graph.module(b).edges()[0].target, Some(a));
+    assert_eq!(graph.post_order(a), vec![b, a]);
+    assert_eq!(graph.post_order(b), vec![a, b]);
+
+    let again = graph.add_entry("b.css", "", &mut resolver);
+    assert_eq!(again, b);
+    assert_eq!(graph.len(), 2);
+}
+
+#[test]
+fn bare_requests_and_warnings() {
+    let mut resolver = MemoryResolver::new();
+    resolver
+        .insert(
+            "src/index.css",
+            "@import 'base.css';\n@import 'https://example.com/a.css';\n.a { color: red",
+        )
+        .insert("src/base.css", ".b {}")
+        .insert("base.css", ".root {}");
+    let mut graph = ModuleGraph::new(Mode::Local);
+    let entry = graph.add_entry(
+        "src/index.css",
+        resolver.get("src/index.css").unwrap().to_string(),
+        &mut resolver,
+    );
+    let edges = graph.module(entry).edges();
+    assert_eq!(edges[0].target, graph.get("src/base.css"));
+    assert_eq!(edges[1].target, None);
+    assert_eq!(graph.len(), 2);
+    let warnings: Vec<_> = graph
+        .module(entry)
+        .warnings()
+        .iter()
+        .map(|w| w.to_string())
+        .collect();
+    assert_eq!(warnings, ["'{' is never closed"]);
+    assert!(graph.module(edges[0].target.unwrap()).warnings().is_empty());
+}
+
+#[test]
+fn closure_resolver() {
+    let mut calls = Vec::new();
+    let mut resolver = |request: &str, importer: &str| {
+        calls.push(format!("{importer} -> {request}"));
+        Some(Resolved {
+            id: request.to_string(),
+            source: String::new(),
+        })
+    };
+    let mut graph = ModuleGraph::new(Mode::Local);
+    graph.add_entry(
+        "entry.css",
+        ".a { composes: b from 'x.css'; composes: c from 'x.css'; }",
+        &mut resolver,
+    );
+    assert_eq!(calls, vec!["entry.css -> x.css"]);
+    assert_eq!(graph.len(), 2);
+}
+
+fn cyclic_graph() -> ModuleGraph {
+    let mut resolver = MemoryResolver::new();
+    resolver
+        .insert(
+            "a.css",
+            "@import './b.css';\n.a { composes: b from './b.css'; color: var(--c from './c.css'); }",
+        )
+        .insert("b.css", "@import './a.css';\n.b {}")
+        .insert("c.css", ":import('./d.css') { x: y; }\n:root { --c: red; }")
+        .insert("d.css", "@import './d.css';");
+    let mut graph = ModuleGraph::new(Mode::Local);
+    graph.add_entry(
+        "a.css",
+        resolver.get("a.css").unwrap().to_string(),
+        &mut resolver,
+    );
+    graph
+}
+
+#[test]
+fn cycles() {
+    let graph = cyclic_graph();
+    let id = |id| graph.get(id).unwrap();
+    assert_eq!(
+        graph.cycles(),
+        vec![vec![id("a.css"), id("b.css")], vec![id("d.css")]]
+    );
+    let mut warnings: Vec<(ModuleId, String)> = Vec::new();
+    graph.cycle_warnings(|module_id, warning| warnings.push((module_id, warning.to_string())));
+    assert_eq!(
+        warnings,
+        vec![
+            (
+                id("a.css"),
+                "Dependency on './b.css' is circular through 2 modules".to_string()
+            ),
+            (
+                id("d.css"),
+                "Dependency on './d.css' is circular".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn dot_and_json() {
+    let graph = cyclic_graph();
+    assert_eq!(
+        graph.to_dot(),
+        indoc! {r#"
+            digraph {
+              "a.css";
+              "b.css";
+              "c.css";
+              "d.css";
+              "a.css" -> "b.css" [label="import", color=red];
+              "a.css" -> "b.css" [label="composes", color=red];
+              "a.css" -> "c.css" [label="var"];
+              "b.css" -> "a.css" [label="import", color=red];
+              "c.css" -> "d.css" [label="icss"];
+              "d.css" -> "d.css" [label="import", color=red];
+            }
+        "#}
+    );
+    assert_eq!(
+        graph.to_json(),
+        concat!(
+            r#"{"modules":{"#,
+            r#""a.css":[{"target":"b.css","kind":"import"},{"target":"b.css","kind":"composes"},{"target":"c.css","kind":"var"}],"#,
+            r#""b.css":[{"target":"a.css","kind":"import"}],"#,
+            r#""c.css":[{"target":"d.css","kind":"icss"}],"#,
+            r#""d.css":[{"target":"d.css","kind":"import"}]},"#,
+            r#""cycles":[["a.css","b.css"],["d.css"]]}"#
+        )
+    );
+}
@@ -0,0 +1,182 @@
+use css_module_lexer::collect_dependencies;
+use css_module_lexer::IncrementalLex;
+use css_module_lexer::Mode;
+use css_module_lexer::OwnedDependency;
+use css_module_lexer::OwnedWarning;
+use css_module_lexer::Range;
+use css_module_lexer::Replacement;
+use indoc::indoc;
+
+const BOOTSTRAP: &str = include_str!("../fixtures/bootstrap.css");
+
+const INPUT: &str = indoc! {r#"
+    @import url("a.css") layer(base) supports(display: grid) screen;
+    :import("./colors.css") { primary: blue; }
+    :export { primary: primary; }
+    .a { color: red } div { color: blue }
+    @media (min-width: 100px) { .b { color: red } .c:global(.d) { color: blue } }
+    @media print { .p { color: red } }
+    div, span { color: red }
+    .e, div, .f { composes: g h from "./g.css"; animation: fade 1s; }
+    @keyframes fade { from { opacity: 0 } to { opacity: 1 } }
+    :global .i { list-style: --counter; background: url(./i.png) }
+    .ä { content: "日本" } /* 😀 */ #j { color: var(--k) }
+    @font-palette-values --l { font-family: Bixa } .m { font-palette: --l }
+"#};
+
+const FRAGMENTS: &[&str] = &[
+    "{",
+    "}",
+    ";",
+    "(",
+    ")",
+    "\"",
+    "'",
+    "/*",
+    "*/",
+    " ",
+    "\n",
+    ".x",
+    "#y",
+    ":global",
+    ":local(",
+    "url(",
+    "@media",
+    "@import \"z.css\";",
+    "composes: a;",
+    "animation: n 1s",
+    ",",
+    "ä",
+    "--v",
+    ".q { color: red }",
+    "\\",
+    "-",
+];
+
+// xorshift64, the tests need to be reproducible rather than good randomness
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn floor_char_boundary(input: &str, mut pos: usize) -> usize {
+    while !input.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    pos
+}
+
+fn collect(input: &str, mode: Mode) -> (Vec<OwnedDependency>, Vec<OwnedWarning>) {
+    let (dependencies, warnings) = collect_dependencies(input, mode);
+    (
+        dependencies.into_iter().map(|d| d.into_owned()).collect(),
+        warnings.into_iter().map(|w| w.into_owned()).collect(),
+    )
+}
+
+fn random_edit(rng: &mut Rng, input: &str) -> (String, Replacement<'static>) {
+    let start = floor_char_boundary(input, rng.below(input.len() + 1));
+    let end = floor_char_boundary(input, (start + rng.below(24)).min(input.len()));
+    let end = end.max(start);
+    let content = if rng.below(4) == 0 {
+        String::new()
+    } else {
+        (0..1 + rng.below(3))
+            .map(|_| FRAGMENTS[rng.below(FRAGMENTS.len())])
+            .collect()
+    };
+    let edited = format!("{}{}{}", &input[..start], content, &input[end..]);
+    let replacement = Replacement::new(Range::new(start as u32, end as u32), content);
+    (edited, replacement)
+}
+
+fn assert_edits(input: &str, mode: Mode, seed: u64, edits: usize) {
+    let mut rng = Rng(seed);
+    let mut input = input.to_string();
+    let mut incremental = IncrementalLex::new(&input, mode);
+    for i in 0..edits {
+        let (edited, replacement) = random_edit(&mut rng, &input);
+        incremental.edit(&edited, &replacement);
+        input = edited;
+        let (dependencies, warnings) = collect(&input, mode);
+        similar_asserts::assert_eq!(
+            (incremental.dependencies(), incremental.warnings()),
+            (dependencies.as_slice(), warnings.as_slice()),
+            "mode {mode:?}, seed {seed}, edit {i}: {replacement:?}\n{input}"
+        );
+    }
+}
+
+#[test]
+fn random_edits() {
+    for mode in [Mode::Local, Mode::Global, Mode::Pure, Mode::Css] {
+        for seed in 1..=50 {
+            assert_edits(INPUT, mode, seed, 100);
+        }
+    }
+}
+
+#[test]
+fn random_edits_bootstrap() {
+    let input = &BOOTSTRAP[..20000];
+    assert_edits(input, Mode::Local, 42, 50);
+    assert_edits(input, Mode::Pure, 43, 50);
+}
+
+#[test]
+fn lexes_only_the_edited_rule() {
+    let mut incremental = IncrementalLex::new(BOOTSTRAP, Mode::Local);
+    let start = BOOTSTRAP.find(".btn-primary {").unwrap();
+    let edit = Replacement::new(Range::new(start as u32 + 1, start as u32 + 4), "button");
+    let input = format!(
+        "{}button{}",
+        &BOOTSTRAP[..start + 1],
+        &BOOTSTRAP[start + 4..]
+    );
+    let relexed = incremental.edit(&input, &edit);
+    assert!(relexed.end - relexed.start < 1000, "{relexed:?}");
+    assert_eq!(
+        (
+            incremental.dependencies().to_vec(),
+            incremental.warnings().to_vec()
+        ),
+        collect(&input, Mode::Local)
+    );
+}
+
+#[test]
+fn invalid_edit_lexes_everything_again() {
+    let input = ".a { color: red } .b {}";
+    let edited = ".a { color: red } .c {} .b {}";
+    for edit in [
+        // Start after end
+        Replacement::new(Range::new(18, 10), ".c {} "),
+        // Past the end of the previous input
+        Replacement::new(Range::new(18, 100), ".c {} "),
+        // Doesn't lead to the edited input
+        Replacement::new(Range::new(0, 0), ".c {} "),
+    ] {
+        let mut incremental = IncrementalLex::new(input, Mode::Local);
+        assert_eq!(
+            incremental.edit(edited, &edit),
+            Range::new(0, edited.len() as u32)
+        );
+        assert_eq!(
+            (
+                incremental.dependencies().to_vec(),
+                incremental.warnings().to_vec()
+            ),
+            collect(edited, Mode::Local)
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use css_module_lexer::collect_dependencies;
+use css_module_lexer::lex_dependencies_from_reader;
+use css_module_lexer::LineCol;
+use css_module_lexer::LineIndex;
+use css_module_lexer::Mode;
+use css_module_lexer::Range;
+
+#[test]
+fn line_breaks() {
+    let input = "a\r\nb\rc\x0cd\n\ne";
+    let index = LineIndex::new(input);
+    assert_eq!(index.line_count(), 6);
+    let lines: Vec<_> = (0..index.line_count())
+        .map(|line| index.line_range(line).unwrap())
+        .map(|range| &input[range.start as usize..range.end as usize])
+        .collect();
+    assert_eq!(lines, ["a", "b", "c", "d", "", "e"]);
+    assert_eq!(index.line_range(6), None);
+    assert_eq!(index.line_col(0), LineCol::new(0, 0));
+    assert_eq!(index.line_col(1), LineCol::new(0, 1));
+    assert_eq!(index.line_col(2), LineCol::new(0, 2));
+    assert_eq!(index.line_col(3), LineCol::new(1, 0));
+    assert_eq!(index.line_col(9), LineCol::new(4, 0));
+    assert_eq!(index.line_col(10), LineCol::new(5, 0));
+    assert_eq!(index.line_col(11), LineCol::new(5, 1));
+    assert_eq!(index.line_col(100), LineCol::new(5, 1));
+    assert_eq!(index.pos(LineCol::new(0, 1)), Some(1));
+    assert_eq!(index.pos(LineCol::new(0, 2)), None);
+    assert_eq!(index.pos(LineCol::new(5, 1)), Some(11));
+    assert_eq!(index.pos(LineCol::new(6, 0)), None);
+}
+
+#[test]
+fn utf16() {
+    // `é` is 2 bytes and 1 UTF-16 code unit, `😀` is 4 bytes and 2 UTF-16 code units
+    let input = "é😀a\n.😀b {}";
+    let index = LineIndex::new(input);
+    assert_eq!(index.line_col(2), LineCol::new(0, 1));
+    assert_eq!(index.line_col_utf8(2), LineCol::new(0, 2));
+    assert_eq!(index.line_col(6), LineCol::new(0, 3));
+    assert_eq!(index.line_col_utf8(6), LineCol::new(0, 6));
+    assert_eq!(index.line_col(13), LineCol::new(1, 3));
+    // Inside `😀`
+    assert_eq!(index.line_col(4), LineCol::new(0, 1));
+
+    assert_eq!(index.pos(LineCol::new(0, 3)), Some(6));
+    assert_eq!(index.pos(LineCol::new(0, 2)), None);
+    assert_eq!(index.pos_utf8(LineCol::new(0, 6)), Some(6));
+    assert_eq!(index.pos_utf8(LineCol::new(0, 4)), None);
+    assert_eq!(index.pos_utf8(LineCol::new(0, 8)), None);
+
+    for (pos, _) in input.char_indices().chain([(input.len(), ' ')]) {
+        let pos = pos as u32;
+        let js_index = index.utf16_index(pos);
+        let expected: usize = input[..pos as usize].chars().map(char::len_utf16).sum();
+        assert_eq!(js_index as usize, expected);
+        assert_eq!(index.pos_from_utf16_index(js_index), Some(pos));
+        assert_eq!(index.pos(index.line_col(pos)), Some(pos));
+        assert_eq!(index.pos_utf8(index.line_col_utf8(pos)), Some(pos));
+    }
+    assert_eq!(index.pos_from_utf16_index(2), None);
+    assert_eq!(index.pos_from_utf16_index(13), None);
+}
+
+#[test]
+fn locations() {
+    let input = ".a {}\n/* 😀 */ .b { color: red; }\n.c { background: url(a b) }";
+    let index = LineIndex::new(input);
+    let (dependencies, warnings) = collect_dependencies(input, Mode::Local);
+    let locations: Vec<_> = dependencies
+        .iter()
+        .filter_map(|d| d.range())
+        .map(|range| range.location("a.css", &index).to_string())
+        .collect();
+    assert_eq!(locations, ["a.css:1:1", "a.css:2:10", "a.css:3:1"]);
+    assert_eq!(warnings.len(), 1);
+    let location = warnings[0].location("a.css", &index);
+    assert_eq!(location.to_string(), "a.css:3:18");
+    assert_eq!(location.start, LineCol::new(2, 17));
+    assert_eq!(location.end, LineCol::new(2, 25));
+
+    let mut owned = Vec::new();
+    lex_dependencies_from_reader(input.as_bytes(), Mode::Local, |_| {}, |w| owned.push(w)).unwrap();
+    assert_eq!(owned[0].location("a.css", &index), location);
+    assert_eq!(
+        Range::new(0, 0).location("a.css", &index).to_string(),
+        "a.css:1:1"
+    );
+}
@@ -0,0 +1,132 @@
+use css_module_lexer::LocalNameKind;
+use css_module_lexer::LocalNameTemplate;
+use css_module_lexer::MemoryResolver;
+use css_module_lexer::Mode;
+use css_module_lexer::ModuleGraph;
+use indoc::indoc;
+
+#[test]
+fn template() {
+    let template = LocalNameTemplate::new("[name]__[local]___[hash:base64:5]");
+    let name = template.render("src/button.module.css", "primary");
+    let (prefix, hash) = name.split_at(name.len() - 5);
+    assert_eq!(prefix, "button_module__primary___");
+    assert!(hash
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    assert_eq!(template.render("src/button.module.css", "primary"), name);
+    assert_ne!(template.render("src/card.module.css", "primary"), name);
+    assert_ne!(
+        template
+            .clone()
+            .with_salt("1")
+            .render("src/button.module.css", "primary"),
+        name
+    );
+
+    let longer = template.with_longer_hash().unwrap();
+    let longer_name = longer.render("src/button.module.css", "primary");
+    assert_eq!(longer_name.len(), name.len() + 1);
+    assert!(longer_name.starts_with(&name));
+
+    assert_eq!(LocalNameTemplate::new("[local]").render("a.css", "b"), "b");
+    assert_eq!(
+        LocalNameTemplate::new("h[hash:hex:8]")
+            .render("a.css", "b")
+            .len(),
+        9
+    );
+    assert!(LocalNameTemplate::new("[hash:hex:16]")
+        .with_longer_hash()
+        .is_none());
+    assert_eq!(
+        LocalNameTemplate::new("[unknown]-[name]").render("1.css", "b"),
+        "_unknown_-1"
+    );
+    assert_eq!(
+        LocalNameTemplate::new("[name]-[local]").render("1.css", "b"),
+        "_1-b"
+    );
+}
+
+fn graph() -> ModuleGraph {
+    let mut resolver = MemoryResolver::new();
+    resolver
+        .insert(
+            "a.css",
+            indoc! {r#"
+                @import "./b.css";
+                .button { color: red; }
+                .button:hover { color: blue; }
+                @keyframes fade {}
+                :root { --gap: 1px; }
+            "#},
+        )
+        .insert(
+            "b.css",
+            indoc! {r#"
+                .button { color: green; }
+                #button {}
+                @keyframes fade {}
+                :root { --gap: 2px; }
+            "#},
+        );
+    let mut graph = ModuleGraph::new(Mode::Local);
+    graph.add_entry(
+        "a.css",
+        resolver.get("a.css").unwrap().to_string(),
+        &mut resolver,
+    );
+    graph
+}
+
+#[test]
+fn collisions() {
+    let graph = graph();
+    let a = graph.get("a.css").unwrap();
+    let b = graph.get("b.css").unwrap();
+    let collisions = graph.find_name_collisions(|_, local| local.to_string());
+    let reported: Vec<_> = collisions
+        .iter()
+        .map(|c| {
+            let first = graph.module(c.first.module).source();
+            let second = graph.module(c.second.module).source();
+            (
+                c.kind,
+                c.name.as_str(),
+                c.first.module,
+                &first[c.first.range.start as usize..c.first.range.end as usize],
+                c.second.module,
+                &second[c.second.range.start as usize..c.second.range.end as usize],
+            )
+        })
+        .collect();
+    assert_eq!(
+        reported,
+        vec![
+            (LocalNameKind::Class, "button", a, ".button", b, ".button"),
+            (LocalNameKind::Keyframes, "fade", a, "fade", b, "fade"),
+            (LocalNameKind::CustomProperty, "gap", a, "--gap", b, "--gap"),
+        ]
+    );
+
+    let template = LocalNameTemplate::new("[name]_[local]");
+    assert!(graph
+        .find_name_collisions(|path, local| template.render(path, local))
+        .is_empty());
+}
+
+#[test]
+fn retry() {
+    let graph = graph();
+    let (template, collisions) = graph.resolve_name_collisions(LocalNameTemplate::new("x"), 3);
+    assert_eq!(template, LocalNameTemplate::new("x").with_salt("3"));
+    assert_eq!(collisions.len(), 3);
+
+    let (template, collisions) =
+        graph.resolve_name_collisions(LocalNameTemplate::new("[hash:hex:1]"), 20);
+    assert!(collisions.is_empty());
+    assert!(graph
+        .find_name_collisions(|path, local| template.render(path, local))
+        .is_empty());
+}
@@ -1,8 +1,8 @@
 use css_module_lexer::Dependency;
+use css_module_lexer::EditBuffer;
 use css_module_lexer::LexDependencies;
 use css_module_lexer::Lexer;
 use css_module_lexer::Mode;
-use css_module_lexer::Range;
 use css_module_lexer::Warning;
 use indoc::indoc;
 use linked_hash_map::LinkedHashMap;
@@ -16,44 +16,33 @@ fn generate_local_name(name: &str) -> String {
 
 impl Scope {
     pub fn transform<'s>(&self, input: &'s str) -> (String, Vec<Warning<'s>>) {
-        let mut result = String::new();
+        let mut buffer = EditBuffer::new(input);
         let mut warnings = Vec::new();
-        let mut index = 0;
         let mut lexer = Lexer::new(input);
         let mut exports = LinkedHashMap::new();
         let mut visitor = LexDependencies::new(
             |dependency| match dependency {
                 Dependency::LocalClass { name, range, .. } => {
-                    result += Lexer::slice_range(input, &Range::new(index, range.start)).unwrap();
                     let name = &name[1..];
-                    result += ".";
                     let new_name = generate_local_name(name);
-                    result += &new_name;
+                    buffer.replace(range, format!(".{new_name}")).unwrap();
                     exports.insert(name.to_string(), vec![new_name]);
-                    index = range.end;
                 }
                 Dependency::LocalId { name, range, .. } => {
-                    result += Lexer::slice_range(input, &Range::new(index, range.start)).unwrap();
                     let name = &name[1..];
-                    result += "#";
                     let new_name = generate_local_name(name);
-                    result += &new_name;
+                    buffer.replace(range, format!("#{new_name}")).unwrap();
                     exports.insert(name.to_string(), vec![new_name]);
-                    index = range.end;
                 }
                 Dependency::LocalKeyframes { name, range } => {
-                    result += Lexer::slice_range(input, &Range::new(index, range.start)).unwrap();
                     let new_name = generate_local_name(name);
-                    result += &new_name;
+                    buffer.replace(range, new_name.clone()).unwrap();
                     exports.insert(name.to_string(), vec![new_name]);
-                    index = range.end;
                 }
                 Dependency::LocalKeyframesDecl { name, range } => {
-                    result += Lexer::slice_range(input, &Range::new(index, range.start)).unwrap();
                     let new_name = generate_local_name(name);
-                    result += &new_name;
+                    buffer.replace(range, new_name.clone()).unwrap();
                     exports.insert(name.to_string(), vec![new_name]);
-                    index = range.end;
                 }
                 Dependency::Composes {
                     local_classes,
@@ -82,9 +71,7 @@ impl Scope {
                     if original.starts_with(":export") || original.starts_with(":import(") {
                         return;
                     }
-                    result += Lexer::slice_range(input, &Range::new(index, range.start)).unwrap();
-                    result += content;
-                    index = range.end;
+                    buffer.replace(range, content).unwrap();
                 }
                 _ => {}
             },
@@ -92,10 +79,7 @@ impl Scope {
             Mode::Global,
         );
         lexer.lex(&mut visitor);
-        let len = input.len() as u32;
-        if index != len {
-            result += Lexer::slice_range(input, &Range::new(index, len)).unwrap();
-        }
+        let mut result = buffer.to_string();
         if !exports.is_empty() {
             result += "\n:export {\n";
             for (key, value) in exports {
@@ -112,13 +96,13 @@ impl Scope {
 }
 
 fn test(input: &str, expected: &str) {
-    let (actual, warnings) = Scope::default().transform(input);
+    let (actual, warnings) = Scope.transform(input);
     assert!(warnings.is_empty(), "{}", &warnings[0]);
     similar_asserts::assert_eq!(expected, actual);
 }
 
 fn test_with_warning(input: &str, expected: &str, warning: &str) {
-    let (actual, warnings) = Scope::default().transform(input);
+    let (actual, warnings) = Scope.transform(input);
     assert!(
         warnings[0].to_string().contains(warning),
         "{}",
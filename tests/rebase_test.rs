@@ -0,0 +1,155 @@
+use css_module_lexer::rebase_url;
+use css_module_lexer::rebase_urls;
+use css_module_lexer::unescape;
+use css_module_lexer::Bundler;
+use css_module_lexer::MemoryResolver;
+use css_module_lexer::Mode;
+use css_module_lexer::ModuleGraph;
+use indoc::indoc;
+use similar_asserts::assert_eq;
+
+#[test]
+fn urls() {
+    let rebase = |url| rebase_url(url, "theme/button.css", "dist/app.css");
+    assert_eq!(rebase("./img.png").as_deref(), Some("../theme/img.png"));
+    assert_eq!(rebase("img.png").as_deref(), Some("../theme/img.png"));
+    assert_eq!(rebase("../img.png").as_deref(), Some("../img.png"));
+    assert_eq!(
+        rebase("./font.woff?v=1#iefix").as_deref(),
+        Some("../theme/font.woff?v=1#iefix")
+    );
+    assert_eq!(
+        rebase_url("./img.png", "theme/button.css", "theme/dark/app.css").as_deref(),
+        Some("../img.png")
+    );
+    assert_eq!(
+        rebase_url("./img.png", "theme/button.css", "theme/app.css").as_deref(),
+        Some("./img.png")
+    );
+    for url in [
+        "",
+        "data:image/png;base64,AAAA",
+        "DATA:text/plain,a",
+        "/img.png",
+        "//cdn.example.com/img.png",
+        "https://example.com/img.png",
+        "#filter",
+        "?query",
+    ] {
+        assert_eq!(rebase(url), None, "{url}");
+    }
+}
+
+#[test]
+fn unescapes() {
+    assert_eq!(unescape("a\\28 b\\)c"), "a(b)c");
+    assert_eq!(unescape("a\\\nb"), "ab");
+    assert_eq!(unescape("\\1F600"), "😀");
+    assert_eq!(unescape("plain"), "plain");
+}
+
+#[test]
+fn rewrites_keep_quoting() {
+    let input = indoc! {r#"
+        @import "./base.css";
+        @import url(./print.css) print;
+        @import /* comment */ url( './theme.css' ) layer(theme);
+        @import "https://example.com/font.css";
+        .a {
+            background: url(./img.png), url("./img 2.png");
+            mask: url('./mask.svg#a');
+            content: image-set("./a.png" 1x, './b.png' 2x, url(./c.png) 3x);
+            cursor: url(data:image/png;base64,AAAA), url(/abs.cur), url(#frag);
+        }
+    "#};
+    assert_eq!(
+        rebase_urls(input, Mode::Css, "theme/button.css", "dist/app.css"),
+        indoc! {r#"
+            @import "../theme/base.css";
+            @import url(../theme/print.css) print;
+            @import /* comment */ url('../theme/theme.css') layer(theme);
+            @import "https://example.com/font.css";
+            .a {
+                background: url(../theme/img.png), url("../theme/img 2.png");
+                mask: url('../theme/mask.svg#a');
+                content: image-set("../theme/a.png" 1x, '../theme/b.png' 2x, url(../theme/c.png) 3x);
+                cursor: url(data:image/png;base64,AAAA), url(/abs.cur), url(#frag);
+            }
+        "#}
+    );
+}
+
+#[test]
+fn escaping() {
+    let input = indoc! {r#"
+        .a {
+            a: url(img.png);
+            b: url("img.png");
+            c: url('it\'s.png');
+            d: url(a\(1\).png);
+        }
+    "#};
+    assert_eq!(
+        rebase_urls(input, Mode::Css, "a.css", "my dir/(x)/b.css"),
+        indoc! {r#"
+            .a {
+                a: url(../../img.png);
+                b: url("../../img.png");
+                c: url('../../it\'s.png');
+                d: url(../../a\(1\).png);
+            }
+        "#}
+    );
+    assert_eq!(
+        rebase_urls(input, Mode::Css, "my dir/(x)/a.css", "b.css"),
+        indoc! {r#"
+            .a {
+                a: url(my\ dir/\(x\)/img.png);
+                b: url("my dir/(x)/img.png");
+                c: url('my dir/(x)/it\'s.png');
+                d: url(my\ dir/\(x\)/a\(1\).png);
+            }
+        "#}
+    );
+}
+
+#[test]
+fn bundle() {
+    let mut resolver = MemoryResolver::new();
+    resolver
+        .insert(
+            "src/index.css",
+            "@import '../theme/button.css';\n.a { background: url(./a.png); }\n",
+        )
+        .insert(
+            "theme/button.css",
+            "@import 'missing.css';\n.b { background: url(./b.png); }\n",
+        );
+    let mut graph = ModuleGraph::new(Mode::Css);
+    let entry = graph.add_entry(
+        "src/index.css",
+        resolver.get("src/index.css").unwrap().to_string(),
+        &mut resolver,
+    );
+    let mut warnings = Vec::new();
+    let output = Bundler::new(&graph)
+        .rebase_urls("dist/app.css")
+        .bundle(entry, |id, warning| {
+            warnings.push((graph.module(id).id().to_string(), warning.to_string()))
+        });
+    assert_eq!(
+        output,
+        indoc! {r#"
+            @import '../theme/missing.css';
+            .b { background: url(../theme/b.png); }
+            .a { background: url(../src/a.png); }
+        "#}
+    );
+    assert_eq!(
+        warnings,
+        vec![(
+            "theme/button.css".to_string(),
+            "Can't resolve 'missing.css', it is kept as an external import".to_string()
+        )]
+    );
+}
@@ -0,0 +1,251 @@
+use css_module_lexer::apply_replacements;
+use css_module_lexer::apply_replacements_with_source_map;
+use css_module_lexer::collect_dependencies;
+use css_module_lexer::source_mapping_url;
+use css_module_lexer::Dependency;
+use css_module_lexer::EditError;
+use css_module_lexer::InputSourceMap;
+use css_module_lexer::LineCol;
+use css_module_lexer::Mode;
+use css_module_lexer::OriginalLocation;
+use css_module_lexer::Range;
+use css_module_lexer::Replacement;
+use css_module_lexer::SourceMap;
+use css_module_lexer::SourceMapBuilder;
+use indoc::indoc;
+
+fn scope(input: &str) -> Vec<Replacement> {
+    let (dependencies, warnings) = collect_dependencies(input, Mode::Local);
+    assert!(warnings.is_empty());
+    dependencies
+        .into_iter()
+        .filter_map(|dependency| match dependency {
+            Dependency::LocalClass { name, range, .. } => {
+                Some(Replacement::new(range, format!("._{}", &name[1..])).with_name(&name[1..]))
+            }
+            Dependency::Replace { content, range } => Some(Replacement::new(range, content)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn mappings(input: &str, replacements: &[Replacement]) -> Vec<(LineCol, LineCol, Option<String>)> {
+    let (_, map) = apply_replacements_with_source_map(input, "input.css", replacements).unwrap();
+    map.mappings()
+        .iter()
+        .map(|m| {
+            (
+                m.generated,
+                m.original,
+                m.name.map(|n| map.names()[n as usize].clone()),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn scoped() {
+    let input = indoc! {r#"
+        .a {
+          color: red;
+        }
+        :global(.b) .c😀d {}
+    "#};
+    let replacements = scope(input);
+    let (output, map) =
+        apply_replacements_with_source_map(input, "input.css", &replacements).unwrap();
+    assert_eq!(output, apply_replacements(input, &replacements).unwrap());
+    similar_asserts::assert_eq!(
+        output,
+        indoc! {r#"
+            ._a {
+              color: red;
+            }
+            .b ._c😀d {}
+        "#}
+    );
+    assert_eq!(map.sources(), ["input.css"]);
+    assert_eq!(map.sources_content(), [Some(input.to_string())]);
+    assert_eq!(map.names(), ["a", "c😀d"]);
+    let lc = LineCol::new;
+    assert_eq!(
+        mappings(input, &replacements),
+        [
+            (lc(0, 0), lc(0, 0), Some("a".to_string())),
+            (lc(0, 3), lc(0, 2), None),
+            (lc(1, 0), lc(1, 0), None),
+            (lc(2, 0), lc(2, 0), None),
+            (lc(3, 0), lc(3, 8), None),
+            (lc(3, 2), lc(3, 11), None),
+            (lc(3, 3), lc(3, 12), Some("c😀d".to_string())),
+            (lc(3, 9), lc(3, 17), None),
+        ]
+    );
+    assert_eq!(
+        map.to_json(),
+        r#"{"version":3,"sources":["input.css"],"sourcesContent":[".a {\n  color: red;\n}\n:global(.b) .c😀d {}\n"],"names":["a","c😀d"],"mappings":"AAAAA,GAAE;AACF;AACA;AACQ,EAAG,CAACC,MAAK"}"#
+    );
+}
+
+#[test]
+fn unordered_replacements_and_insertions() {
+    let input = "a{}\nb{}";
+    let replacements = [
+        Replacement::new(Range::new(4, 5), "multi\nline"),
+        Replacement::new(Range::new(0, 0), "/* banner */\n"),
+        Replacement::new(Range::new(1, 3), ""),
+    ];
+    let (output, map) =
+        apply_replacements_with_source_map(input, "input.css", &replacements).unwrap();
+    assert_eq!(output, "/* banner */\na\nmulti\nline{}");
+    let lc = LineCol::new;
+    assert_eq!(
+        mappings(input, &replacements),
+        [
+            (lc(0, 0), lc(0, 0), None),
+            (lc(1, 0), lc(0, 0), None),
+            (lc(1, 1), lc(0, 3), None),
+            (lc(2, 0), lc(1, 0), None),
+            (lc(3, 4), lc(1, 1), None),
+        ]
+    );
+    assert_eq!(map.encode_mappings(), "AAAA;AAAA,CAAG;AACH;IAAC");
+}
+
+#[test]
+fn overlapping_replacements() {
+    let input = ":global(.a) .b {}";
+    let replacements = [
+        Replacement::new(Range::new(0, 8), ""),
+        Replacement::new(Range::new(7, 10), "x"),
+    ];
+    let error = EditError::Overlap {
+        range: Range::new(7, 10),
+        existing: Range::new(0, 8),
+    };
+    assert_eq!(apply_replacements(input, &replacements), Err(error.clone()));
+    assert_eq!(
+        apply_replacements_with_source_map(input, "input.css", &replacements).map(|(s, _)| s),
+        Err(error)
+    );
+    assert_eq!(
+        apply_replacements(input, &[Replacement::new(Range::new(10, 30), "")]),
+        Err(EditError::OutOfBounds {
+            range: Range::new(10, 30),
+        })
+    );
+}
+
+fn scss_map() -> SourceMap {
+    // `.a {\n  .b { color: red; }\n}` compiled to `.a .b {\n  color: red;\n}`
+    let mut builder = SourceMapBuilder::new(Some("input.css"));
+    let source = builder.add_source("input.scss", Some(".a {\n  .b { color: red; }\n}\n"));
+    let lc = LineCol::new;
+    builder.add_mapping(lc(0, 0), source, lc(0, 0), None);
+    let name = builder.add_name("b");
+    builder.add_mapping(lc(0, 3), source, lc(1, 2), Some(name));
+    builder.add_mapping(lc(1, 2), source, lc(1, 7), None);
+    builder.build()
+}
+
+#[test]
+fn parse_source_map() {
+    let map = scss_map();
+    let json = map.to_json();
+    assert_eq!(
+        json,
+        r#"{"version":3,"file":"input.css","sources":["input.scss"],"sourcesContent":[".a {\n  .b { color: red; }\n}\n"],"names":["b"],"mappings":"AAAA,GACEA;EAAK"}"#
+    );
+    assert_eq!(SourceMap::from_json(&json), Some(map.clone()));
+    assert_eq!(
+        SourceMap::from_data_url(&map.to_data_url()),
+        Some(map.clone())
+    );
+    let percent_encoded = "data:application/json,%7B%22version%22%3A3%2C%22sources%22%3A%5B%22a.scss%22%5D%2C%22names%22%3A%5B%5D%2C%22mappings%22%3A%22AAAA%22%7D";
+    let parsed = SourceMap::from_data_url(percent_encoded).unwrap();
+    assert_eq!(parsed.sources(), ["a.scss"]);
+    assert_eq!(parsed.sources_content(), [None]);
+    let with_root = SourceMap::from_json(
+        r#"{"version":3,"sourceRoot":"src","sources":["a.scss",null],"names":[],"mappings":"A,AAAA;;ACCA"}"#,
+    )
+    .unwrap();
+    assert_eq!(with_root.sources(), ["src/a.scss", ""]);
+    assert!(with_root
+        .to_json()
+        .contains(r#""sources":["src/a.scss",null]"#));
+    assert_eq!(with_root.mappings().len(), 2);
+    assert_eq!(with_root.mappings()[1].generated, LineCol::new(2, 0));
+    assert_eq!(with_root.mappings()[1].original, LineCol::new(1, 0));
+    assert!(SourceMap::from_json(r#"{"version":2,"sources":[],"mappings":""}"#).is_none());
+    assert!(SourceMap::from_json(r#"{"version":3,"sources":[],"mappings":"AAAA"}"#).is_none());
+    assert!(SourceMap::from_json(&"[".repeat(1_000_000)).is_none());
+}
+
+#[test]
+fn inline_source_map() {
+    let map = scss_map();
+    let input = format!(
+        ".a .b {{\n  color: red;\n}}\n/*# sourceMappingURL={} */\n",
+        map.to_data_url()
+    );
+    assert_eq!(source_mapping_url(&input), Some(map.to_data_url().as_str()));
+    assert_eq!(
+        source_mapping_url("a {}\n/*# sourceMappingURL=a.css.map */"),
+        Some("a.css.map")
+    );
+    assert_eq!(source_mapping_url("a {}"), None);
+
+    let input_map = InputSourceMap::from_inline(&input).unwrap();
+    let (dependencies, _) = collect_dependencies(&input, Mode::Local);
+    let locations: Vec<_> = dependencies
+        .iter()
+        .filter_map(|d| d.range())
+        .filter_map(|range| input_map.original_location(range))
+        .collect();
+    assert_eq!(
+        locations,
+        [
+            OriginalLocation {
+                source: "input.scss",
+                position: LineCol::new(0, 0),
+                name: None,
+            },
+            OriginalLocation {
+                source: "input.scss",
+                position: LineCol::new(1, 2),
+                name: Some("b"),
+            },
+        ]
+    );
+    assert_eq!(
+        input_map.original_location(&Range::new(10, 11)),
+        Some(OriginalLocation {
+            source: "input.scss",
+            position: LineCol::new(1, 7),
+            name: None,
+        })
+    );
+
+    let replacements = scope(&input);
+    let (output, output_map) =
+        apply_replacements_with_source_map(&input, "input.css", &replacements).unwrap();
+    assert!(output.starts_with("._a ._b {\n"));
+    let composed = input_map.compose(&output_map);
+    assert_eq!(composed.sources(), ["input.scss"]);
+    assert_eq!(composed.names(), ["a", "b"]);
+    let lc = LineCol::new;
+    let composed_mappings: Vec<_> = composed
+        .mappings()
+        .iter()
+        .map(|m| (m.generated, m.original, m.name))
+        .collect();
+    assert_eq!(
+        composed_mappings,
+        [
+            (lc(0, 0), lc(0, 0), Some(0)),
+            (lc(0, 3), lc(0, 0), None),
+            (lc(0, 4), lc(1, 2), Some(1)),
+            (lc(0, 7), lc(1, 2), None),
+        ]
+    );
+}
@@ -0,0 +1,111 @@
+use css_module_lexer::collect_dependencies;
+use css_module_lexer::lex_dependencies_from_reader;
+use css_module_lexer::Mode;
+use css_module_lexer::OwnedDependency;
+use css_module_lexer::OwnedWarning;
+use css_module_lexer::StreamLexer;
+use indoc::indoc;
+
+const BOOTSTRAP: &str = include_str!("../fixtures/bootstrap.css");
+const BOOTSTRAP_MIN: &str = include_str!("../fixtures/bootstrap.min.css");
+
+const EDGE_CASES: &str = indoc! {r#"
+    @charset "utf-8";
+    @import url("a.css") layer(base) supports(display: grid) screen;
+    @import "b.css";
+    :import("./colors.css") { primary: blue; }
+    :export { primary: primary; }
+    .ä { color: red } div { color: blue }
+    @media (min-width: 100px) { .a { color: red } .b:global(.c) { color: blue } }
+    .d, div, .e { composes: f g from "./f.css"; animation: fade 1s; }
+    @media print { .p { color: red } }
+    div { color: red }
+    @keyframes fade { from { opacity: 0 } to { opacity: 1 } }
+    @import "late.css";
+    } .stray { color: red }
+    :global .g { list-style: --counter; }
+    .h { background: url( data:image/png;base64,iVBORw0KGgo ); content: "日本\
+    語" }
+    /* 😀 comment */ .i { x: "unterminated
+    }
+    .k { background: url(a b) } .l { c: d } .m { background: url(a(b) }
+    .n { c: d }
+    .j { a: b; c: d; background: url(a b }
+"#};
+
+fn collect(input: &str, mode: Mode) -> (Vec<OwnedDependency>, Vec<OwnedWarning>) {
+    let (dependencies, warnings) = collect_dependencies(input, mode);
+    (
+        dependencies.into_iter().map(|d| d.into_owned()).collect(),
+        warnings.into_iter().map(|w| w.into_owned()).collect(),
+    )
+}
+
+fn stream(input: &str, mode: Mode, chunk_size: usize) -> (Vec<OwnedDependency>, Vec<OwnedWarning>) {
+    let mut dependencies = Vec::new();
+    let mut warnings = Vec::new();
+    let mut lexer = StreamLexer::new(mode, |d| dependencies.push(d), |w| warnings.push(w));
+    for chunk in input.as_bytes().chunks(chunk_size) {
+        lexer.write(chunk).unwrap();
+    }
+    lexer.finish().unwrap();
+    (dependencies, warnings)
+}
+
+fn assert_same(input: &str, chunk_sizes: &[usize]) {
+    for mode in [Mode::Local, Mode::Global, Mode::Pure, Mode::Css] {
+        let expected = collect(input, mode);
+        for &chunk_size in chunk_sizes {
+            similar_asserts::assert_eq!(
+                stream(input, mode, chunk_size),
+                expected,
+                "mode {mode:?}, chunk size {chunk_size}"
+            );
+        }
+    }
+}
+
+#[test]
+fn edge_cases() {
+    assert_same(EDGE_CASES, &[1, 2, 3, 5, 16, 17, 64, 1000]);
+}
+
+#[test]
+fn wrapped() {
+    // Split inside the blocks, and at the end of input where they are never closed
+    let wrapped = format!("@media screen {{ @layer base {{\n{EDGE_CASES}}} }}\n");
+    assert_same(&wrapped, &[1, 3, 16, 17, 64]);
+    let unclosed = format!("@media screen {{ @supports (display: grid) {{\n{EDGE_CASES}");
+    assert_same(&unclosed, &[1, 3, 16, 17, 64]);
+}
+
+#[test]
+fn bootstrap() {
+    assert_same(BOOTSTRAP, &[100, 4096, 65536]);
+    assert_same(BOOTSTRAP_MIN, &[100, 4096, 65536]);
+}
+
+#[test]
+fn reader() {
+    let mut dependencies = Vec::new();
+    let mut warnings = Vec::new();
+    lex_dependencies_from_reader(
+        BOOTSTRAP.as_bytes(),
+        Mode::Local,
+        |d| dependencies.push(d),
+        |w| warnings.push(w),
+    )
+    .unwrap();
+    assert_eq!((dependencies, warnings), collect(BOOTSTRAP, Mode::Local));
+}
+
+#[test]
+fn invalid_utf8() {
+    let mut lexer = StreamLexer::new(Mode::Local, |_| {}, |_| {});
+    assert!(lexer.write(b".a { content: \"\xff\" }").is_err());
+
+    let mut lexer = StreamLexer::new(Mode::Local, |_| {}, |_| {});
+    lexer.write(".a { content: \"".as_bytes()).unwrap();
+    lexer.write(&"日".as_bytes()[..2]).unwrap();
+    assert!(lexer.finish().is_err());
+}
@@ -6,6 +6,7 @@ use css_module_lexer::Lexer;
 use css_module_lexer::Mode;
 use css_module_lexer::UrlRangeKind;
 use css_module_lexer::Warning;
+use css_module_lexer::WarningKind;
 use indoc::indoc;
 use smallvec::SmallVec;
 
@@ -29,7 +30,7 @@ fn assert_url_dependency(
         kind: k,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*req, request);
     assert_eq!(*k, kind);
@@ -53,7 +54,7 @@ fn assert_import_dependency(
         media: actual_media,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_request, request);
     assert_eq!(*actual_layer, layer);
@@ -69,7 +70,7 @@ fn assert_local_class_dependency(input: &str, dependency: &Dependency, name: &st
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(*actual_explicit, explicit);
@@ -83,7 +84,7 @@ fn assert_local_id_dependency(input: &str, dependency: &Dependency, name: &str,
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(*actual_explicit, explicit);
@@ -100,12 +101,19 @@ fn assert_local_var_dependency(
         name: actual_name,
         range,
         from: actual_from,
+        from_range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(*actual_from, from);
+    assert_eq!(
+        from_range
+            .as_ref()
+            .map(|range| Lexer::slice_range(input, range).unwrap()),
+        from
+    );
     assert_eq!(
         Lexer::slice_range(input, range).unwrap(),
         format!("--{}", name)
@@ -118,7 +126,7 @@ fn assert_local_var_decl_dependency(input: &str, dependency: &Dependency, name:
         name: actual_name,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(
@@ -133,7 +141,7 @@ fn assert_local_property_decl_dependency(input: &str, dependency: &Dependency, n
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(
@@ -148,7 +156,7 @@ fn assert_local_keyframes_decl_dependency(input: &str, dependency: &Dependency,
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(Lexer::slice_range(input, range).unwrap(), name);
@@ -160,7 +168,7 @@ fn assert_local_keyframes_dependency(input: &str, dependency: &Dependency, name:
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(Lexer::slice_range(input, range).unwrap(), name);
@@ -172,7 +180,7 @@ fn assert_local_counter_style_decl_dependency(input: &str, dependency: &Dependen
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(Lexer::slice_range(input, range).unwrap(), name);
@@ -184,7 +192,7 @@ fn assert_local_counter_style_dependency(input: &str, dependency: &Dependency, n
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(Lexer::slice_range(input, range).unwrap(), name);
@@ -196,7 +204,7 @@ fn assert_local_font_palette_decl_dependency(input: &str, dependency: &Dependenc
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(
@@ -211,7 +219,7 @@ fn assert_local_font_palette_dependency(input: &str, dependency: &Dependency, na
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_name, name);
     assert_eq!(
@@ -235,7 +243,7 @@ fn assert_composes_dependency(
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(
         *actual_local_classes,
@@ -260,7 +268,7 @@ fn assert_replace_dependency(
         range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_content, content);
     assert_eq!(Lexer::slice_range(input, range).unwrap(), range_content);
@@ -268,7 +276,7 @@ fn assert_replace_dependency(
 
 fn assert_icss_import_from_dependency(_input: &str, dependency: &Dependency, path: &str) {
     let Dependency::ICSSImportFrom { path: actual_path } = dependency else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_path, path);
 }
@@ -284,14 +292,14 @@ fn assert_icss_import_value_dependency(
         value: actual_value,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_prop, prop);
     assert_eq!(*actual_value, value);
 }
 
 fn assert_icss_export_value_dependency(
-    _input: &str,
+    input: &str,
     dependency: &Dependency,
     prop: &str,
     value: &str,
@@ -299,12 +307,14 @@ fn assert_icss_export_value_dependency(
     let Dependency::ICSSExportValue {
         prop: actual_prop,
         value: actual_value,
+        range,
     } = dependency
     else {
-        return assert!(false);
+        panic!();
     };
     assert_eq!(*actual_prop, prop);
     assert_eq!(*actual_value, value);
+    assert_eq!(Lexer::slice_range(input, range).unwrap(), prop);
 }
 
 #[test]
@@ -416,6 +426,112 @@ fn not_preceded_at_import() {
     assert_warning(input, &warnings[0], "@import");
 }
 
+#[test]
+fn bad_url_and_string() {
+    let input = indoc! {r#"
+        a { background: url(./a b.png), url(./c(d).png), url( ./e.png ); }
+        b { content: "abc
+        }
+    "#};
+    let (dependencies, warnings) = collect_dependencies(input, Mode::Css);
+    assert_eq!(warnings.len(), 3);
+    assert!(matches!(warnings[0].kind(), WarningKind::BadUrl));
+    assert_warning(input, &warnings[0], "url(./a b.png)");
+    assert!(matches!(warnings[1].kind(), WarningKind::BadUrl));
+    assert_warning(input, &warnings[1], "url(./c(d)");
+    assert!(matches!(warnings[2].kind(), WarningKind::BadString));
+    assert_warning(input, &warnings[2], "\"abc");
+    assert_eq!(
+        warnings[2].to_string(),
+        "Unterminated string, newlines must be escaped"
+    );
+    assert_eq!(dependencies.len(), 1);
+    assert_url_dependency(
+        input,
+        &dependencies[0],
+        "./e.png",
+        UrlRangeKind::Function,
+        "url( ./e.png )",
+    );
+}
+
+#[test]
+fn unterminated_comment() {
+    let input = "a { color: red; } /* b { color: blue; } *";
+    let (_, warnings) = collect_dependencies(input, Mode::Local);
+    assert_eq!(warnings.len(), 1);
+    assert!(matches!(
+        warnings[0].kind(),
+        WarningKind::UnterminatedComment
+    ));
+    assert_warning(input, &warnings[0], "/* b { color: blue; } *");
+}
+
+#[test]
+fn unterminated_comment_skipped_while_lexing_dependencies() {
+    // Comments after `composes:` and after `{` are skipped outside of the lexer's own loop
+    for input in [".a { composes: b /* x", ".a { /* x", ".a { composes: /* x"] {
+        let (_, warnings) = collect_dependencies(input, Mode::Local);
+        let comments: Vec<_> = warnings
+            .iter()
+            .filter(|warning| matches!(warning.kind(), WarningKind::UnterminatedComment))
+            .collect();
+        assert_eq!(comments.len(), 1, "{input}");
+        assert_warning(input, comments[0], "/* x");
+    }
+}
+
+#[test]
+fn unbalanced_blocks() {
+    let input = indoc! {r#"
+        a { color: red; } }
+        :import("./a.css") { i: b; }
+        :export { e: f; }
+        @media screen {
+          b { width: calc(1px + (2px
+    "#};
+    let (_, warnings) = collect_dependencies(input, Mode::Local);
+    assert_eq!(warnings.len(), 5);
+    assert!(matches!(
+        warnings[0].kind(),
+        WarningKind::UnexpectedClosingBrace
+    ));
+    assert_warning(input, &warnings[0], "}");
+    assert_eq!(warnings[0].range().start, 18);
+    let unclosed: Vec<_> = warnings[1..]
+        .iter()
+        .map(|warning| {
+            let WarningKind::UnclosedBlock { opening } = warning.kind() else {
+                panic!("expected an unclosed block");
+            };
+            assert_warning(input, warning, opening);
+            *opening
+        })
+        .collect();
+    assert_eq!(unclosed, vec!["{", "{", "calc(", "("]);
+    assert_eq!(warnings[3].to_string(), "'calc(' is never closed");
+}
+
+#[test]
+fn unclosed_parenthesis_before_block() {
+    let input = "a( { color: red }";
+    let (_, warnings) = collect_dependencies(input, Mode::Local);
+    assert_eq!(warnings.len(), 1);
+    assert_warning(input, &warnings[0], "a(");
+}
+
+#[test]
+fn unmatched_right_parenthesis_after_mode_class() {
+    let input = ":global) .a {}";
+    let (dependencies, _) = collect_dependencies(input, Mode::Local);
+    assert_eq!(dependencies.len(), 2);
+    assert!(matches!(dependencies[0], Dependency::Replace { .. }));
+    assert!(matches!(
+        dependencies[1],
+        Dependency::LocalClass { name: ".a", .. }
+    ));
+}
+
 #[test]
 fn url_string() {
     let input = indoc! {r#"
@@ -519,7 +635,7 @@ fn expect_url() {
     "#};
     let (dependencies, warnings) = collect_dependencies(input, Mode::Css);
     assert!(dependencies.is_empty());
-    assert_warning(&input, &warnings[0], "@import ;");
+    assert_warning(input, &warnings[0], "@import ;");
 }
 
 #[test]
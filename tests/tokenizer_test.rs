@@ -0,0 +1,127 @@
+use css_module_lexer::TokenKind;
+use css_module_lexer::Tokenizer;
+use indoc::indoc;
+use similar_asserts::assert_eq;
+
+fn snapshot(input: &str) -> String {
+    let mut result = String::new();
+    let mut end = 0;
+    for token in Tokenizer::new(input) {
+        assert_eq!(token.range.start, end);
+        assert_eq!(
+            &input[token.range.start as usize..token.range.end as usize],
+            token.text
+        );
+        end = token.range.end;
+        if token.kind != TokenKind::Whitespace {
+            result += &format!("{:?}: {}\n", token.kind, token.text);
+        }
+    }
+    assert_eq!(end as usize, input.len());
+    result
+}
+
+#[test]
+fn tokens() {
+    let input = indoc! {r#"
+        @import url( ./a.css ) screen;
+        /* comment */
+        .a:hover > #b, [data-x="y"] {
+          width: calc(100% - -1.5e3px + .5em);
+          background: url("./b.png") url(bad url) URL(c.png);
+          content: 'x\'y';
+        }
+        <!-- --> + 1e #fff @ \66oo
+    "#};
+    assert_eq!(
+        snapshot(input),
+        indoc! {r#"
+            AtKeyword: @import
+            Url: url( ./a.css )
+            Ident: screen
+            Semicolon: ;
+            Comment: /* comment */
+            Delim: .
+            Ident: a
+            Colon: :
+            Ident: hover
+            Delim: >
+            Hash: #b
+            Comma: ,
+            LeftSquareBracket: [
+            Ident: data-x
+            Delim: =
+            String: "y"
+            RightSquareBracket: ]
+            LeftCurlyBracket: {
+            Ident: width
+            Colon: :
+            Function: calc(
+            Percentage: 100%
+            Delim: -
+            Dimension: -1.5e3px
+            Delim: +
+            Dimension: .5em
+            RightParenthesis: )
+            Semicolon: ;
+            Ident: background
+            Colon: :
+            Function: url(
+            String: "./b.png"
+            RightParenthesis: )
+            BadUrl: url(bad url)
+            Url: URL(c.png)
+            Semicolon: ;
+            Ident: content
+            Colon: :
+            String: 'x\'y'
+            Semicolon: ;
+            RightCurlyBracket: }
+            Cdo: <!--
+            Cdc: -->
+            Delim: +
+            Dimension: 1e
+            Hash: #fff
+            Delim: @
+            Ident: \66oo
+        "#}
+    );
+}
+
+#[test]
+fn end_of_input() {
+    assert_eq!(snapshot(""), "");
+    assert_eq!(snapshot("a"), "Ident: a\n");
+    assert_eq!(snapshot("-a"), "Ident: -a\n");
+    assert_eq!(snapshot("-"), "Delim: -\n");
+    assert_eq!(snapshot("#"), "Delim: #\n");
+    assert_eq!(snapshot("#a"), "Hash: #a\n");
+    assert_eq!(snapshot("@a"), "AtKeyword: @a\n");
+    assert_eq!(snapshot("10px"), "Dimension: 10px\n");
+    assert_eq!(snapshot("1"), "Number: 1\n");
+    assert_eq!(snapshot("\"abc"), "String: \"abc\n");
+    assert_eq!(snapshot("/* a *"), "Comment: /* a *\n");
+    assert_eq!(snapshot("url(a"), "Url: url(a\n");
+    assert_eq!(snapshot("url( "), "Url: url( \n");
+    assert_eq!(snapshot("f("), "Function: f(\n");
+    assert_eq!(snapshot(".a é"), "Delim: .\nIdent: a\nIdent: é\n");
+}
+
+#[test]
+fn lookahead() {
+    let input = "a { color: red } b { }";
+    let mut selectors = Vec::new();
+    let mut tokens = Tokenizer::new(input)
+        .filter(|t| t.kind != TokenKind::Whitespace)
+        .peekable();
+    while let Some(token) = tokens.next() {
+        if token.kind == TokenKind::Ident
+            && tokens
+                .peek()
+                .is_some_and(|next| next.kind == TokenKind::LeftCurlyBracket)
+        {
+            selectors.push(token.text);
+        }
+    }
+    assert_eq!(selectors, vec!["a", "b"]);
+}
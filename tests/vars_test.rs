@@ -0,0 +1,116 @@
+use css_module_lexer::EditBuffer;
+use css_module_lexer::MemoryResolver;
+use css_module_lexer::Mode;
+use css_module_lexer::ModuleGraph;
+use indoc::indoc;
+use similar_asserts::assert_eq;
+
+#[test]
+fn resolve_vars() {
+    let input = indoc! {r#"
+        .a {
+            color: var(--primary from "./tokens.css", red);
+            background: var(--surface from './tokens.css');
+            border-color: var(--border from global);
+            outline-color: var(--missing from "./tokens.css");
+            width: var(--size from "./missing.css");
+            height: var(--local);
+        }
+    "#};
+    let mut resolver = MemoryResolver::new();
+    resolver.insert(
+        "tokens.css",
+        indoc! {r#"
+            :root {
+                --primary: blue;
+            }
+            @property --surface {
+                syntax: "<color>";
+                inherits: false;
+                initial-value: white;
+            }
+        "#},
+    );
+    let mut graph = ModuleGraph::new(Mode::Local);
+    let entry = graph.add_entry("index.css", input, &mut resolver);
+    let tokens = graph.get("tokens.css").unwrap();
+    let mut warnings = Vec::new();
+    let vars = graph.resolve_vars(
+        entry,
+        |id, name| format!("{}_{name}", id.trim_end_matches(".css")),
+        |id, warning| warnings.push((id, warning)),
+    );
+    let resolved: Vec<_> = vars
+        .iter()
+        .map(|var| (var.name, var.module, var.resolved.as_str()))
+        .collect();
+    assert_eq!(
+        resolved,
+        vec![
+            ("primary", Some(tokens), "--tokens_primary"),
+            ("surface", Some(tokens), "--tokens_surface"),
+            ("border", None, "--border"),
+        ]
+    );
+
+    let mut buffer = EditBuffer::new(input);
+    for var in &vars {
+        buffer
+            .replace(var.range.clone(), var.resolved.clone())
+            .unwrap();
+    }
+    assert_eq!(
+        buffer.to_string(),
+        indoc! {r#"
+            .a {
+                color: var(--tokens_primary, red);
+                background: var(--tokens_surface);
+                border-color: var(--border);
+                outline-color: var(--missing from "./tokens.css");
+                width: var(--size from "./missing.css");
+                height: var(--local);
+            }
+        "#}
+    );
+
+    let warnings: Vec<_> = warnings
+        .iter()
+        .map(|(id, warning)| {
+            assert_eq!(*id, entry);
+            let range = warning.range();
+            (
+                warning.to_string(),
+                &input[range.start as usize..range.end as usize],
+            )
+        })
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            (
+                "Custom property '--missing' is not declared in './tokens.css'".to_string(),
+                "--missing from \"./tokens.css\"",
+            ),
+            (
+                "Custom property '--size' is not declared in './missing.css'".to_string(),
+                "--size from \"./missing.css\"",
+            ),
+        ]
+    );
+}
+
+#[test]
+fn from_range() {
+    let input = r#".a { color: var(--primary from /* "./tokens.css" */ "./tokens.css"); }"#;
+    let mut resolver = MemoryResolver::new();
+    resolver.insert("tokens.css", ":root { --primary: blue; }");
+    let mut graph = ModuleGraph::new(Mode::Local);
+    let entry = graph.add_entry("index.css", input, &mut resolver);
+    let vars = graph.resolve_vars(entry, |_, name| format!("x_{name}"), |_, _| {});
+    assert_eq!(vars.len(), 1);
+    let mut buffer = EditBuffer::new(input);
+    buffer
+        .replace(vars[0].range.clone(), vars[0].resolved.clone())
+        .unwrap();
+    assert_eq!(buffer.to_string(), ".a { color: var(--x_primary); }");
+}
@@ -0,0 +1,368 @@
+use css_module_lexer::collect_dependencies;
+use css_module_lexer::Fanout;
+use css_module_lexer::LexDependencies;
+use css_module_lexer::Lexer;
+use css_module_lexer::Mode;
+use css_module_lexer::Pos;
+use css_module_lexer::Utf8Bytes;
+use css_module_lexer::Visitor;
+use indoc::indoc;
+use similar_asserts::assert_eq;
+
+#[derive(Default)]
+struct Selectors<'s> {
+    in_block: bool,
+    selectors: Vec<&'s str>,
+}
+
+impl<'s> Visitor<'s> for Selectors<'s> {
+    fn is_selector(&mut self, _: &mut Lexer<'s>) -> Option<bool> {
+        Some(!self.in_block)
+    }
+
+    fn class(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.selectors.push(lexer.slice(start, end)?);
+        Some(())
+    }
+
+    fn id(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.selectors.push(lexer.slice(start, end)?);
+        Some(())
+    }
+
+    fn left_curly_bracket(&mut self, _: &mut Lexer<'s>, _: Pos, _: Pos) -> Option<()> {
+        self.in_block = true;
+        Some(())
+    }
+
+    fn right_curly_bracket(&mut self, _: &mut Lexer<'s>, _: Pos, _: Pos) -> Option<()> {
+        self.in_block = false;
+        Some(())
+    }
+}
+
+#[test]
+fn custom_visitor() {
+    let input = indoc! {r#"
+        .a, #b:hover { color: #fff; width: .5em; }
+        .c .d {}
+    "#};
+    let mut visitor = Selectors::default();
+    Lexer::new(input).lex(&mut visitor);
+    assert_eq!(visitor.selectors, vec![".a", "#b", ".c", ".d"]);
+}
+
+#[test]
+fn default_methods() {
+    struct AtKeywords<'s>(Vec<&'s str>);
+
+    impl<'s> Visitor<'s> for AtKeywords<'s> {
+        fn at_keyword(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+            self.0.push(lexer.slice(start, end)?);
+            Some(())
+        }
+    }
+
+    let input = "@import url(a.css); @media print { .a { color: #000 } }";
+    let mut visitor = AtKeywords(Vec::new());
+    Lexer::new(input).lex(&mut visitor);
+    assert_eq!(visitor.0, vec!["@import", "@media"]);
+}
+
+#[test]
+fn stop_lexing() {
+    struct FirstString(Option<(Pos, Pos)>);
+
+    impl<'s> Visitor<'s> for FirstString {
+        fn string(&mut self, _: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+            self.0 = Some((start, end));
+            None
+        }
+    }
+
+    let mut visitor = FirstString(None);
+    // The default iterator of the lexer can be named
+    let mut lexer: Lexer<Utf8Bytes> = Lexer::new(r#"a { content: "x" "y" }"#);
+    lexer.lex(&mut visitor);
+    assert_eq!(visitor.0, Some((13, 16)));
+    assert_eq!(lexer.cur_pos(), Some(16));
+}
+
+#[test]
+fn fanout() {
+    let input = indoc! {r#"
+        .a { composes: b c from "./b.css"; color: #fff; }
+        #d .e { background: url(./e.png); content: "x" "y"; }
+        @media print { @supports (display: grid) { .f {} } }
+    "#};
+    let mut dependencies = Vec::new();
+    let mut warnings = Vec::new();
+    let mut lex_dependencies =
+        LexDependencies::new(|d| dependencies.push(d), |w| warnings.push(w), Mode::Local);
+    // The observer's own `is_selector` is never asked, the primary decides
+    let mut selectors = Selectors {
+        in_block: true,
+        selectors: Vec::new(),
+    };
+    let mut at_keywords = 0;
+    let mut counter = Counter(&mut at_keywords);
+    let mut first_string = StringObserver(None);
+    let mut fanout = Fanout::new(&mut lex_dependencies)
+        .observe(&mut selectors)
+        .observe(&mut counter)
+        .observe(&mut first_string);
+    let mut lexer = Lexer::new(input);
+    lexer.lex(&mut fanout);
+    assert_eq!(lexer.cur(), None);
+
+    let (expected_dependencies, expected_warnings) = collect_dependencies(input, Mode::Local);
+    assert_eq!(dependencies, expected_dependencies);
+    assert_eq!(warnings, expected_warnings);
+    // `b` and `c` are consumed by the primary while lexing `composes` and never reported
+    assert_eq!(selectors.selectors, vec![".a", "#d", ".e", ".f"]);
+    assert_eq!(at_keywords, 2);
+    assert_eq!(first_string.0, Some((93, 96)));
+}
+
+struct Counter<'a>(&'a mut usize);
+
+impl<'s> Visitor<'s> for Counter<'_> {
+    fn at_keyword(&mut self, _: &mut Lexer<'s>, _: Pos, _: Pos) -> Option<()> {
+        *self.0 += 1;
+        Some(())
+    }
+}
+
+struct StringObserver(Option<(Pos, Pos)>);
+
+impl<'s> Visitor<'s> for StringObserver {
+    fn string(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        assert!(self.0.is_none());
+        self.0 = Some((start, end));
+        // Side effects on the lexer don't leak out of an observer
+        while lexer.cur().is_some() {
+            lexer.consume();
+        }
+        None
+    }
+}
+
+#[derive(Default)]
+struct AllTokens {
+    in_block: bool,
+    tokens: String,
+}
+
+impl AllTokens {
+    fn add(&mut self, kind: &str, lexer: &Lexer, start: Pos, end: Pos) -> Option<()> {
+        self.tokens += &format!("{kind}: {}\n", lexer.slice(start, end)?);
+        Some(())
+    }
+}
+
+impl<'s> Visitor<'s> for AllTokens {
+    fn is_selector(&mut self, _: &mut Lexer<'s>) -> Option<bool> {
+        Some(!self.in_block)
+    }
+
+    fn ident(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("ident", lexer, start, end)
+    }
+
+    fn function(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("function", lexer, start, end)
+    }
+
+    fn at_keyword(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("at_keyword", lexer, start, end)
+    }
+
+    fn class(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("class", lexer, start, end)
+    }
+
+    fn id(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("id", lexer, start, end)
+    }
+
+    fn hash(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("hash", lexer, start, end)
+    }
+
+    fn pseudo_class(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("pseudo_class", lexer, start, end)
+    }
+
+    fn semicolon(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("semicolon", lexer, start, end)
+    }
+
+    fn right_parenthesis(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("right_parenthesis", lexer, start, end)
+    }
+
+    fn left_curly_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.in_block = true;
+        self.add("left_curly", lexer, start, end)
+    }
+
+    fn right_curly_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.in_block = false;
+        self.add("right_curly", lexer, start, end)
+    }
+
+    fn left_square_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("left_square", lexer, start, end)
+    }
+
+    fn right_square_bracket(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("right_square", lexer, start, end)
+    }
+
+    fn number(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("number", lexer, start, end)
+    }
+
+    fn percentage(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("percentage", lexer, start, end)
+    }
+
+    fn dimension(
+        &mut self,
+        lexer: &mut Lexer<'s>,
+        start: Pos,
+        end: Pos,
+        unit_start: Pos,
+    ) -> Option<()> {
+        let unit = lexer.slice(unit_start, end)?;
+        self.add(&format!("dimension ({unit})"), lexer, start, end)
+    }
+
+    fn unicode_range(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("unicode_range", lexer, start, end)
+    }
+
+    fn colon(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("colon", lexer, start, end)
+    }
+
+    fn cdo(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("cdo", lexer, start, end)
+    }
+
+    fn cdc(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("cdc", lexer, start, end)
+    }
+
+    fn delim(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("delim", lexer, start, end)
+    }
+
+    fn whitespace(&mut self, _: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.tokens += &format!("whitespace: {}\n", end - start);
+        Some(())
+    }
+
+    fn comment(&mut self, lexer: &mut Lexer<'s>, start: Pos, end: Pos) -> Option<()> {
+        self.add("comment", lexer, start, end)
+    }
+}
+
+#[test]
+fn all_tokens() {
+    let input = indoc! {r#"
+        <!--/* a */u+b[x]>.c:hover{width:calc(50% + -1.5e2px*2);unicode-range:U+0-7F,u+4??;color:#fff/* b *//* c */}-->
+    "#};
+    let mut visitor = AllTokens::default();
+    Lexer::new(input).lex(&mut visitor);
+    assert_eq!(
+        visitor.tokens,
+        indoc! {r#"
+            cdo: <!--
+            comment: /* a */
+            ident: u
+            delim: +
+            ident: b
+            left_square: [
+            ident: x
+            right_square: ]
+            delim: >
+            class: .c
+            pseudo_class: :hover
+            left_curly: {
+            ident: width
+            colon: :
+            function: calc(
+            percentage: 50%
+            whitespace: 1
+            delim: +
+            whitespace: 1
+            dimension (px): -1.5e2px
+            delim: *
+            number: 2
+            right_parenthesis: )
+            semicolon: ;
+            ident: unicode-range
+            colon: :
+            unicode_range: U+0-7F
+            unicode_range: u+4??
+            semicolon: ;
+            ident: color
+            colon: :
+            hash: #fff
+            comment: /* b */
+            comment: /* c */
+            right_curly: }
+            cdc: -->
+            whitespace: 1
+        "#}
+    );
+}
+
+#[test]
+fn tokens_at_end_of_input() {
+    for (input, expected) in [
+        ("1px", "dimension (px): 1px\n"),
+        ("10%", "percentage: 10%\n"),
+        ("1", "number: 1\n"),
+        (".5", "number: .5\n"),
+        ("+1", "number: +1\n"),
+        ("1e3", "number: 1e3\n"),
+        ("#a", "id: #a\n"),
+        ("@media", "at_keyword: @media\n"),
+        ("a", "ident: a\n"),
+        ("-a", "ident: -a\n"),
+        (".b", "class: .b\n"),
+        (":hover", "pseudo_class: :hover\n"),
+        (
+            "{color:#fff",
+            "left_curly: {\nident: color\ncolon: :\nhash: #fff\n",
+        ),
+        ("{u+4??", "left_curly: {\nunicode_range: u+4??\n"),
+    ] {
+        let mut visitor = AllTokens::default();
+        Lexer::new(input).lex(&mut visitor);
+        assert_eq!(visitor.tokens, expected, "{input}");
+    }
+}
+
+#[test]
+fn stop_inside_block() {
+    struct StopAtBlock;
+
+    impl<'s> Visitor<'s> for StopAtBlock {
+        fn left_curly_bracket(&mut self, _: &mut Lexer<'s>, _: Pos, _: Pos) -> Option<()> {
+            None
+        }
+    }
+
+    let mut warnings = Vec::new();
+    let mut lex_dependencies = LexDependencies::new(|_| {}, |w| warnings.push(w), Mode::Local);
+    let mut stop_at_block = StopAtBlock;
+    let mut fanout = Fanout::new(&mut stop_at_block).observe(&mut lex_dependencies);
+    let mut lexer = Lexer::new("a { color: red; }");
+    lexer.lex(&mut fanout);
+    assert_eq!(lexer.cur_pos(), Some(3));
+    // The block is closed after where lexing stopped
+    assert!(warnings.is_empty());
+}